@@ -0,0 +1,151 @@
+use std::collections::BTreeMap;
+
+use indexmap::IndexMap;
+use time::OffsetDateTime;
+
+use crate::{api::query::{Aggregation, AggregationResult, Bucket, BucketKey}, query::FieldVal};
+
+/// Running state of one aggregation, updated with each matching record.
+pub(crate) enum AggState {
+    Count(u64),
+    Terms { size: usize, counts: IndexMap<String, u64> },
+    Histogram { interval: f64, buckets: BTreeMap<i64, u64> },
+    DateHistogram { interval_ns: i128, buckets: BTreeMap<i64, u64> },
+    Min(Option<f64>),
+    Max(Option<f64>),
+    Avg { sum: f64, count: u64 },
+    Sum(f64),
+}
+
+impl AggState {
+    pub fn new(agg: &Aggregation) -> AggState {
+        match *agg {
+            Aggregation::Count => AggState::Count(0),
+            Aggregation::Terms { size, .. } => AggState::Terms { size, counts: IndexMap::new() },
+            Aggregation::Histogram { interval, .. } => AggState::Histogram { interval, buckets: BTreeMap::new() },
+            Aggregation::DateHistogram { interval, .. } => AggState::DateHistogram { interval_ns: ((interval * 1e9) as i128).max(1), buckets: BTreeMap::new() },
+            Aggregation::Min { .. } => AggState::Min(None),
+            Aggregation::Max { .. } => AggState::Max(None),
+            Aggregation::Avg { .. } => AggState::Avg { sum: 0.0, count: 0 },
+            Aggregation::Sum { .. } => AggState::Sum(0.0),
+        }
+    }
+
+    pub fn update(&mut self, val: &FieldVal) {
+        match (self, val) {
+            (AggState::Count(c), _) => *c += 1,
+
+            (AggState::Terms { .. }, FieldVal::Null) => {}
+            (state @ AggState::Terms { .. }, FieldVal::Array(items)) => {
+                for v in items.iter() {
+                    state.update(v);
+                }
+            }
+            (AggState::Terms { counts, .. }, FieldVal::String(s)) => {
+                if let Some(c) = counts.get_mut(*s) {
+                    *c += 1;
+                } else {
+                    counts.insert(s.to_string(), 1);
+                }
+            }
+            (AggState::Terms { counts, .. }, v) => *counts.entry(v.to_string()).or_default() += 1,
+
+            (AggState::Histogram { interval, buckets }, FieldVal::Number(n)) => {
+                *buckets.entry((n / *interval).floor() as i64).or_default() += 1;
+            }
+
+            (AggState::DateHistogram { interval_ns, buckets }, FieldVal::Time(t)) => {
+                // With a tiny interval, the bucket index of a distant time may not fit
+                if let Ok(i) = i64::try_from(t.unix_timestamp_nanos().div_euclid(*interval_ns)) {
+                    *buckets.entry(i).or_default() += 1;
+                }
+            }
+
+            (AggState::Min(m), FieldVal::Number(n)) => *m = Some(m.map_or(*n, |m| m.min(*n))),
+            (AggState::Max(m), FieldVal::Number(n)) => *m = Some(m.map_or(*n, |m| m.max(*n))),
+            (AggState::Avg { sum, count }, FieldVal::Number(n)) => { *sum += n; *count += 1; }
+            (AggState::Sum(s), FieldVal::Number(n)) => *s += n,
+
+            _ => {}
+        }
+    }
+
+    pub fn finish(self) -> AggregationResult {
+        match self {
+            AggState::Count(c) => AggregationResult::Count(c),
+            AggState::Terms { size, counts } => {
+                let mut counts: Vec<_> = counts.into_iter().collect();
+                counts.sort_by(|(ka, ca), (kb, cb)| cb.cmp(ca).then_with(|| natord::compare(ka, kb)));
+                counts.truncate(size);
+                AggregationResult::Buckets(counts.into_iter().map(|(key, count)| {
+                    Bucket { key: BucketKey::String(key), count }
+                }).collect())
+            }
+            AggState::Histogram { interval, buckets } => {
+                AggregationResult::Buckets(buckets.into_iter().map(|(i, count)| {
+                    Bucket { key: BucketKey::Number(i as f64 * interval), count }
+                }).collect())
+            }
+            AggState::DateHistogram { interval_ns, buckets } => {
+                AggregationResult::Buckets(buckets.into_iter().filter_map(|(i, count)| {
+                    let t = OffsetDateTime::from_unix_timestamp_nanos(i as i128 * interval_ns).ok()?;
+                    Some(Bucket { key: BucketKey::Time(t), count })
+                }).collect())
+            }
+            AggState::Min(m) | AggState::Max(m) => AggregationResult::Value(m),
+            AggState::Avg { sum, count } => AggregationResult::Value((count > 0).then(|| sum / count as f64)),
+            AggState::Sum(s) => AggregationResult::Value(Some(s)),
+        }
+    }
+}
+
+#[test]
+fn test_aggregations() {
+    use time::macros::datetime;
+
+    let vals = [FieldVal::String("200"), FieldVal::String("404"), FieldVal::String("200"), FieldVal::Null];
+    let mut terms = AggState::new(&Aggregation::Terms { field: "status".into(), size: 10 });
+    let mut count = AggState::new(&Aggregation::Count);
+    for v in &vals {
+        terms.update(v);
+        count.update(v);
+    }
+    assert_eq!(count.finish(), AggregationResult::Count(4));
+    assert_eq!(terms.finish(), AggregationResult::Buckets(vec![
+        Bucket { key: BucketKey::String("200".into()), count: 2 },
+        Bucket { key: BucketKey::String("404".into()), count: 1 },
+    ]));
+
+    let vals = [FieldVal::Number(5.0), FieldVal::Number(12.0), FieldVal::Number(-1.0), FieldVal::String("x")];
+    let mut hist = AggState::new(&Aggregation::Histogram { field: "n".into(), interval: 10.0 });
+    let mut min = AggState::new(&Aggregation::Min { field: "n".into() });
+    let mut avg = AggState::new(&Aggregation::Avg { field: "n".into() });
+    for v in &vals {
+        hist.update(v);
+        min.update(v);
+        avg.update(v);
+    }
+    assert_eq!(hist.finish(), AggregationResult::Buckets(vec![
+        Bucket { key: BucketKey::Number(-10.0), count: 1 },
+        Bucket { key: BucketKey::Number(0.0), count: 1 },
+        Bucket { key: BucketKey::Number(10.0), count: 1 },
+    ]));
+    assert_eq!(min.finish(), AggregationResult::Value(Some(-1.0)));
+    assert_eq!(avg.finish(), AggregationResult::Value(Some(16.0 / 3.0)));
+
+    let mut date_hist = AggState::new(&Aggregation::DateHistogram { field: "t".into(), interval: 60.0 });
+    date_hist.update(&FieldVal::Time(datetime!(2022-03-30 21:21:23 UTC)));
+    date_hist.update(&FieldVal::Time(datetime!(2022-03-30 21:21:59 UTC)));
+    date_hist.update(&FieldVal::Time(datetime!(2022-03-30 21:22:01 UTC)));
+    assert_eq!(date_hist.finish(), AggregationResult::Buckets(vec![
+        Bucket { key: BucketKey::Time(datetime!(2022-03-30 21:21:00 UTC)), count: 2 },
+        Bucket { key: BucketKey::Time(datetime!(2022-03-30 21:22:00 UTC)), count: 1 },
+    ]));
+
+    let mut date_hist = AggState::new(&Aggregation::DateHistogram { field: "t".into(), interval: 1e-9 });
+    date_hist.update(&FieldVal::Time(datetime!(2022-03-30 21:21:23 UTC)));
+    date_hist.update(&FieldVal::Time(datetime!(9999-01-01 00:00:00 UTC)));
+    assert_eq!(date_hist.finish(), AggregationResult::Buckets(vec![
+        Bucket { key: BucketKey::Time(datetime!(2022-03-30 21:21:23 UTC)), count: 1 },
+    ]));
+}
@@ -15,16 +15,26 @@ pub struct Field {
     pub display: FieldDisplayConfig,
 }
 
-#[derive(Copy, Clone, Serialize)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum FieldType {
     Keyword,
     Phrase,
     Number,
+    Boolean,
     Timestamp,
+    Ip,
 }
 
 #[derive(Clone, Serialize, Deserialize, Default)]
 pub struct FieldDisplayConfig {
     values: Option<Vec<String>>,
-}
\ No newline at end of file
+
+    /// Unit of numeric values, such as `s` or `B`
+    pub(crate) unit: Option<String>,
+}
+
+impl FieldDisplayConfig {
+    /// Keys of the display options in a dataset's field config
+    pub(crate) const KEYS: &'static [&'static str] = &["values", "unit"];
+}
@@ -1,15 +1,120 @@
-use serde::{ Serialize, Deserialize };
+use std::{fmt, net::IpAddr, str::FromStr};
+
+use serde::{ Serialize, Deserialize, Deserializer };
 use indexmap::{IndexMap, IndexSet};
 use time::OffsetDateTime;
 
 #[derive(Deserialize)]
 pub struct Query {
-    pub filter: IndexMap<String, QueryFilter>,
+    #[serde(default)]
+    pub filter: Filter,
+
+    #[serde(default)]
     pub returning: IndexSet<String>,
+
+    #[serde(default)]
+    pub aggregate: IndexMap<String, Aggregation>,
+
+    #[serde(default)]
+    pub sort: Vec<Sort>,
+
+    /// Maximum number of rows to return. If unset, all matching rows are
+    /// returned, unless `aggregate` is non-empty, in which case no rows are.
+    #[serde(default)]
+    pub limit: Option<usize>,
+
+    /// Number of rows to skip before returning rows
+    #[serde(default)]
+    pub offset: usize,
+
+    /// Return only rows after this cursor, taken from `next` in a previous response
+    #[serde(default)]
+    pub after: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq, Deserialize)]
-#[serde(untagged)]
+pub struct Sort {
+    pub field: String,
+
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Boolean combination of field filters.
+///
+/// Deserialized from an object whose keys are field names mapped to a `QueryFilter`,
+/// or the operators `and` and `or` (mapped to a list of `Filter`s) or `not` (mapped
+/// to a `Filter`). All entries of the object must match.
+/// e.g. `{ "or": [{ "status": { "min": 500 } }, { "duration": { "min": 2 } }], "not": { "path": { "is": ["/health"] } } }`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Filter {
+    Field(String, QueryFilter),
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+    Not(Box<Filter>),
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter::And(Vec::new())
+    }
+}
+
+impl<'de> Deserialize<'de> for Filter {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = Filter;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("map of field filters")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(self, mut access: A) -> Result<Filter, A::Error> {
+                let mut all = Vec::new();
+
+                while let Some(key) = access.next_key::<String>()? {
+                    all.push(match &key[..] {
+                        "and" => Filter::And(access.next_value()?),
+                        "or" => Filter::Or(access.next_value()?),
+                        "not" => Filter::Not(access.next_value()?),
+                        _ => Filter::Field(key, access.next_value()?),
+                    });
+                }
+
+                Ok(if all.len() == 1 { all.pop().unwrap() } else { Filter::And(all) })
+            }
+        }
+
+        deserializer.deserialize_map(Visitor)
+    }
+}
+
+#[test]
+fn test_deserialize_filter() {
+    let status = || Filter::Field("status".into(), QueryFilter::Range { min: Some(500.0), max: None });
+    let path = || Filter::Field("path".into(), QueryFilter::KeywordIs { is: ["/health".to_owned()].into_iter().collect() });
+
+    assert_eq!(serde_json::from_str::<Filter>(r#"{}"#).unwrap(), Filter::And(vec![]));
+    assert_eq!(serde_json::from_str::<Filter>(r#"{"status": {"min": 500}}"#).unwrap(), status());
+    assert_eq!(serde_json::from_str::<Filter>(r#"{"status": {"min": 500}, "path": {"is": ["/health"]}}"#).unwrap(), Filter::And(vec![status(), path()]));
+    assert_eq!(serde_json::from_str::<Filter>(r#"{"or": [{"status": {"min": 500}}, {"path": {"is": ["/health"]}}]}"#).unwrap(), Filter::Or(vec![status(), path()]));
+    assert_eq!(serde_json::from_str::<Filter>(r#"{"status": {"min": 500}, "not": {"path": {"is": ["/health"]}}}"#).unwrap(),
+        Filter::And(vec![status(), Filter::Not(Box::new(path()))]));
+    assert!(serde_json::from_str::<Filter>(r#"{"or": {"status": {"min": 500}}}"#).is_err());
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(untagged, deny_unknown_fields)]
 pub enum QueryFilter {
     Present { present: bool }, // { present: true }
     KeywordIs { is: IndexSet<String> }, // { is: [] }
@@ -23,25 +128,194 @@ pub enum QueryFilter {
     TimeSince {
         since: f64, // seconds
     },
+    Contains { // { contains: "...", case_insensitive: false }
+        contains: String,
+        #[serde(default)]
+        case_insensitive: bool,
+    },
+    Prefix { prefix: String }, // { prefix: "..." }
+    Suffix { suffix: String }, // { suffix: "..." }
+    Match { r#match: String }, // { match: "..." }, all words present, ignoring case
+    Regex { // { regex: "...", case_insensitive: false }
+        regex: String,
+        #[serde(default)]
+        case_insensitive: bool,
+    },
+    Cidr { cidr: Vec<Cidr> }, // { cidr: ["10.0.0.0/8", "fd00::/8"] }, address in any of the networks
+    IsTrue { is_true: bool }, // { is_true: true }
     Range { min: Option<f64>, max: Option<f64> }, // { min: ..., max: ... }
 }
 
+/// An IP network such as `10.0.0.0/8`. A bare address matches only itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr.to_canonical(), ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => prefix_eq(u32::from(net).into(), u32::from(ip).into(), 32, self.prefix_len),
+            (IpAddr::V6(net), IpAddr::V6(ip)) => prefix_eq(net.into(), ip.into(), 128, self.prefix_len),
+            _ => false,
+        }
+    }
+}
+
+/// Whether the first `prefix_len` of `bits` bits of `a` and `b` are equal
+fn prefix_eq(a: u128, b: u128, bits: u8, prefix_len: u8) -> bool {
+    (a ^ b).checked_shr(u32::from(bits - prefix_len)).unwrap_or(0) == 0
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix_len) = s.split_once('/').unwrap_or((s, ""));
+        let addr: IpAddr = addr.parse().map_err(|_| format!("invalid IP address `{addr}`"))?;
+        let max_len = if addr.to_canonical().is_ipv4() { 32 } else { 128 };
+
+        let prefix_len = if prefix_len.is_empty() {
+            max_len
+        } else {
+            prefix_len.parse().ok().filter(|&l| l <= max_len).ok_or_else(|| format!("invalid prefix length in `{s}`"))?
+        };
+
+        Ok(Cidr { addr, prefix_len })
+    }
+}
+
+impl<'de> Deserialize<'de> for Cidr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[test]
+fn test_cidr() {
+    let cidr = |s: &str| s.parse::<Cidr>().unwrap();
+    let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+
+    assert!(cidr("10.0.0.0/8").contains(ip("10.255.1.2")));
+    assert!(!cidr("10.0.0.0/8").contains(ip("11.0.0.1")));
+    assert!(cidr("192.168.1.7").contains(ip("192.168.1.7")));
+    assert!(!cidr("192.168.1.7").contains(ip("192.168.1.8")));
+    assert!(cidr("0.0.0.0/0").contains(ip("8.8.8.8")));
+    assert!(!cidr("0.0.0.0/0").contains(ip("::1")));
+    assert!(cidr("10.0.0.0/8").contains(ip("::ffff:10.1.2.3")));
+    assert!(cidr("2001:db8::/32").contains(ip("2001:db8:1::5")));
+    assert!(!cidr("2001:db8::/32").contains(ip("2001:db9::5")));
+    assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+    assert!("10.0.0/8".parse::<Cidr>().is_err());
+}
+
 #[test]
 fn test_deserialize_query_filter() {
     use time::macros::datetime;
     assert_eq!(serde_json::from_str::<QueryFilter>(r#"{"present": true}"#).unwrap(), QueryFilter::Present{present: true});
     assert_eq!(serde_json::from_str::<QueryFilter>(r#"{"min": 5}"#).unwrap(), QueryFilter::Range{min: Some(5.0), max: None});
+    assert_eq!(serde_json::from_str::<QueryFilter>(r#"{"contains": "err"}"#).unwrap(), QueryFilter::Contains{contains: "err".into(), case_insensitive: false});
+    assert_eq!(serde_json::from_str::<QueryFilter>(r#"{"contains": "err", "case_insensitive": true}"#).unwrap(), QueryFilter::Contains{contains: "err".into(), case_insensitive: true});
+    assert_eq!(serde_json::from_str::<QueryFilter>(r#"{"match": "connection refused"}"#).unwrap(), QueryFilter::Match{r#match: "connection refused".into()});
+    assert_eq!(serde_json::from_str::<QueryFilter>(r#"{"regex": "^GET /"}"#).unwrap(), QueryFilter::Regex{regex: "^GET /".into(), case_insensitive: false});
     assert_eq!(serde_json::from_str::<QueryFilter>(r#"{"after": "2022-03-30T21:21:23-06:00", "before": "2022-03-30T21:22:01-06:00"}"#).unwrap(), 
         QueryFilter::TimeRange{after: datetime!(2022-03-30 21:21:23-06:00), before: datetime!(2022-03-30 21:22:01-06:00)});
+    assert_eq!(serde_json::from_str::<QueryFilter>(r#"{"cidr": ["10.0.0.0/8"]}"#).unwrap(), QueryFilter::Cidr{cidr: vec!["10.0.0.0/8".parse().unwrap()]});
+    assert!(serde_json::from_str::<QueryFilter>(r#"{"cidr": ["10.0.0.0/80"]}"#).is_err());
+    assert!(serde_json::from_str::<QueryFilter>(r#"{"mni": 5}"#).is_err());
+    assert_eq!(serde_json::from_str::<QueryFilter>(r#"{"is_true": false}"#).unwrap(), QueryFilter::IsTrue { is_true: false });
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Aggregation {
+    Count, // "count"
+    Terms { // { terms: { field: ..., size: 10 } }
+        field: String,
+        #[serde(default = "default_terms_size")]
+        size: usize,
+    },
+    Histogram { field: String, interval: f64 }, // { histogram: { field: ..., interval: ... } }
+    DateHistogram { field: String, interval: f64 }, // { date_histogram: { field: ..., interval: ... } }, interval in seconds
+    Min { field: String },
+    Max { field: String },
+    Avg { field: String },
+    Sum { field: String },
+}
+
+fn default_terms_size() -> usize { 10 }
+
+impl Aggregation {
+    pub fn field(&self) -> Option<&str> {
+        use Aggregation::*;
+        match self {
+            Count => None,
+            Terms { field, .. } | Histogram { field, .. } | DateHistogram { field, .. } |
+            Min { field } | Max { field } | Avg { field } | Sum { field } => Some(field),
+        }
+    }
+}
+
+#[test]
+fn test_deserialize_aggregation() {
+    assert_eq!(serde_json::from_str::<Aggregation>(r#""count""#).unwrap(), Aggregation::Count);
+    assert_eq!(serde_json::from_str::<Aggregation>(r#"{"terms": {"field": "status"}}"#).unwrap(), Aggregation::Terms{field: "status".into(), size: 10});
+    assert_eq!(serde_json::from_str::<Aggregation>(r#"{"date_histogram": {"field": "ts", "interval": 60}}"#).unwrap(), Aggregation::DateHistogram{field: "ts".into(), interval: 60.0});
 }
 
 #[derive(Serialize)]
 pub struct Response<C> {
-    //pub stats: ResponseStats,
+    pub stats: ResponseStats,
     pub results: C,
+
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    pub aggregations: IndexMap<String, AggregationResult>,
+
+    /// Cursor to pass as `after` to fetch the next page, if more rows remain
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum AggregationResult {
+    Count(u64),
+    Value(Option<f64>),
+    Buckets(Vec<Bucket>),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Bucket {
+    pub key: BucketKey,
+    pub count: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum BucketKey {
+    String(String),
+    Number(f64),
+    Time(#[serde(with = "time::serde::rfc3339")] OffsetDateTime),
+}
+
+#[derive(Serialize, Default)]
 pub struct ResponseStats {
     pub rows_scanned: u64,
+    pub rows_matched: u64,
+    pub bytes_read: u64, // after decompression
+
+    /// Files opened or skipped without reading. Members of archives count as
+    /// files in addition to the archive itself, and a member compressed with
+    /// an unsupported method or with an unreadable path counts as skipped.
+    pub files_opened: u64,
+    pub files_skipped: u64,
+
+    /// Number of files opened with each compression codec, e.g. `gzip`
+    pub codecs: IndexMap<String, u64>,
+
+    /// Number of non-null values each parser failed to parse, by parsed field
+    pub parse_failures: IndexMap<String, u64>,
+
+    pub elapsed: f64, // seconds
 }
\ No newline at end of file
@@ -0,0 +1,394 @@
+use std::{cmp::Ordering, net::IpAddr};
+
+use serde::{Serialize, Deserialize};
+use time::OffsetDateTime;
+
+use crate::{api::query::{Response, ResponseStats, SortOrder}, query::{QueryPlan, FieldVal, QueryError}, aggregate::AggState, ResultSet};
+
+/// Owned copy of a field value used as a sort key.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) enum SortVal {
+    Number(f64),
+    Bool(bool),
+    Time(#[serde(with = "time::serde::rfc3339")] OffsetDateTime),
+    Ip(IpAddr),
+    String(String),
+    Null,
+}
+
+impl SortVal {
+    fn new(v: &FieldVal) -> SortVal {
+        match v {
+            FieldVal::Number(n) => SortVal::Number(*n),
+            FieldVal::Bool(b) => SortVal::Bool(*b),
+            FieldVal::Time(t) => SortVal::Time(*t),
+            FieldVal::Ip(ip) => SortVal::Ip(*ip),
+            FieldVal::String(s) => SortVal::String(s.to_string()),
+            FieldVal::Null | FieldVal::Map(_) | FieldVal::Array(_) => SortVal::Null,
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            SortVal::Number(_) => 0,
+            SortVal::Bool(_) => 1,
+            SortVal::Time(_) => 2,
+            SortVal::Ip(_) => 3,
+            SortVal::String(_) => 4,
+            SortVal::Null => 5,
+        }
+    }
+
+    /// Compare values in the specified order, always placing nulls last.
+    fn cmp(&self, other: &SortVal, order: SortOrder) -> Ordering {
+        let ord = match (self, other) {
+            (SortVal::Null, _) | (_, SortVal::Null) => return self.rank().cmp(&other.rank()),
+            (SortVal::Number(a), SortVal::Number(b)) => a.total_cmp(b),
+            (SortVal::Bool(a), SortVal::Bool(b)) => a.cmp(b),
+            (SortVal::Time(a), SortVal::Time(b)) => a.cmp(b),
+            (SortVal::Ip(a), SortVal::Ip(b)) => a.cmp(b),
+            (SortVal::String(a), SortVal::String(b)) => a.cmp(b),
+            (a, b) => a.rank().cmp(&b.rank()),
+        };
+
+        match order {
+            SortOrder::Asc => ord,
+            SortOrder::Desc => ord.reverse(),
+        }
+    }
+}
+
+/// Identifies a record's position in the result order. Serialized as the opaque pagination cursor.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct RowKey {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    sort: Vec<SortVal>,
+    file: String,
+    offset: u64,
+}
+
+impl RowKey {
+    pub fn parse(cursor: &str) -> Result<RowKey, QueryError> {
+        serde_json::from_str(cursor).map_err(|_| QueryError::InvalidCursor)
+    }
+
+    /// Whether the cursor was produced by a query with this number of sort keys.
+    pub fn matches_sort(&self, sort_keys: usize) -> bool {
+        self.sort.len() == sort_keys
+    }
+
+    fn encode(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Total order of rows for a sorted query. Ties are broken by position so
+    /// that the order, and therefore the cursor, is stable between queries.
+    fn cmp(&self, other: &RowKey, orders: &[SortOrder]) -> Ordering {
+        orders.iter().zip(self.sort.iter().zip(other.sort.iter()))
+            .map(|(&order, (a, b))| a.cmp(b, order))
+            .find(|o| o.is_ne())
+            .unwrap_or_else(|| {
+                natord::compare(&self.file, &other.file).reverse()
+                    .then(self.offset.cmp(&other.offset))
+            })
+    }
+}
+
+/// Split a file name like `logs.tar!/app.log` into the archive path and member path.
+fn split_archive(file: &str) -> (&str, Option<&str>) {
+    match file.split_once("!/") {
+        Some((path, member)) => (path, Some(member)),
+        None => (file, None),
+    }
+}
+
+/// Accumulates the rows and aggregations for the records matched by a query.
+///
+/// Sources feed every record that passes the query filters to `push`, and
+/// may use `wants_file` and `wants_record` to skip reading or parsing
+/// records that can't affect the results.
+pub(crate) struct Collector<'p, 'a> {
+    plan: &'p QueryPlan<'a>,
+    results: ResultSet,
+    aggs: Vec<AggState>,
+    orders: Vec<SortOrder>,
+
+    /// Scan statistics, updated by the source
+    pub stats: ResponseStats,
+
+    /// Parse failure count for each parser in the plan
+    parse_failures: Vec<u64>,
+
+    /// Sorted queries: candidate rows and their index in `results`
+    candidates: Vec<(RowKey, usize)>,
+
+    /// Sorted queries: once compacted, rows that don't sort before this can be discarded
+    threshold: Option<RowKey>,
+
+    /// Sorted queries: number of rows after the cursor, whether or not they were kept
+    matched: usize,
+
+    /// Unsorted queries: number of rows skipped for `offset`
+    skipped: usize,
+
+    /// Unsorted queries: number of rows returned
+    returned: usize,
+
+    /// Unsorted queries: key of the last row returned
+    last: Option<RowKey>,
+
+    /// Unsorted queries: whether a matching row was found after the limit was reached
+    more: bool,
+
+    /// Unsorted queries: whether the scan has reached the file named by the cursor
+    seen_cursor_file: bool,
+}
+
+impl<'p, 'a> Collector<'p, 'a> {
+    pub fn new(plan: &'p QueryPlan<'a>) -> Self {
+        Collector {
+            plan,
+            results: ResultSet::new(plan.returning.keys().map(|n| n.to_string()).collect()),
+            aggs: plan.aggregations.values().map(|a| AggState::new(a.aggregation)).collect(),
+            orders: plan.sort.iter().map(|(_, order)| *order).collect(),
+            stats: ResponseStats::default(),
+            parse_failures: vec![0; plan.parsers.len()],
+            candidates: Vec::new(),
+            threshold: None,
+            matched: 0,
+            skipped: 0,
+            returned: 0,
+            last: None,
+            more: false,
+            seen_cursor_file: false,
+        }
+    }
+
+    fn is_sorted(&self) -> bool {
+        !self.plan.sort.is_empty()
+    }
+
+    /// Whether the remaining records can no longer change the results.
+    pub fn is_done(&self) -> bool {
+        !self.is_sorted() && self.aggs.is_empty() && self.more
+    }
+
+    /// Queries with aggregations return rows only if they set a limit.
+    fn returns_rows(&self) -> bool {
+        self.aggs.is_empty() || self.plan.limit.is_some()
+    }
+
+    fn limit_reached(&self) -> bool {
+        self.plan.limit.is_some_and(|limit| self.returned >= limit)
+    }
+
+    /// Unsorted queries: whether the record at this position was returned
+    /// on a previous page. Positions are compared in scan order, as in
+    /// `RowKey::cmp`, so the file named by the cursor need not still exist.
+    fn before_cursor(&self, file: &str, offset: u64) -> bool {
+        match &self.plan.after {
+            Some(cursor) if !self.is_sorted() => {
+                let (path, member) = split_archive(file);
+                let (cursor_path, cursor_member) = split_archive(&cursor.file);
+
+                // Members of an archive are scanned in the order they are stored
+                let member_order = || match (member, cursor_member) {
+                    (Some(a), Some(b)) if a != b && !self.seen_cursor_file => Ordering::Less,
+                    (Some(a), Some(b)) if a != b => Ordering::Greater,
+                    _ => Ordering::Equal,
+                };
+
+                natord::compare(path, cursor_path).reverse()
+                    .then_with(member_order)
+                    .then(offset.cmp(&cursor.offset))
+                    .is_le()
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether any record from the file may affect the results.
+    pub fn wants_file(&self, file: &str) -> bool {
+        if self.is_done() { return false }
+        !self.aggs.is_empty() || !self.before_cursor(file, u64::MAX)
+    }
+
+    /// Whether the record at this position may affect the results. Must be
+    /// called for each record in order, before `push`.
+    pub fn wants_record(&mut self, file: &str, offset: u64) -> bool {
+        if self.plan.after.as_ref().is_some_and(|c| c.file == file) {
+            self.seen_cursor_file = true;
+        }
+        !self.aggs.is_empty() || !self.before_cursor(file, offset)
+    }
+
+    /// Record that the parser at index `parser` in the plan failed to parse its input.
+    pub fn parse_failed(&mut self, parser: usize) {
+        self.parse_failures[parser] += 1;
+    }
+
+    /// Add a record that passed the query filters.
+    pub fn push(&mut self, file: &str, offset: u64, data: &[&mut [FieldVal]]) {
+        self.stats.rows_matched += 1;
+
+        for (agg, state) in self.plan.aggregations.values().zip(self.aggs.iter_mut()) {
+            let val = agg.field.map_or(FieldVal::Null, |loc| data[loc.parser][loc.field]);
+            state.update(&val);
+        }
+
+        if !self.returns_rows() {
+            return;
+        }
+
+        if self.is_sorted() {
+            self.push_sorted(file, offset, data);
+        } else {
+            if self.before_cursor(file, offset) {
+                return;
+            }
+
+            if self.limit_reached() {
+                self.more = true;
+                return;
+            }
+
+            if self.skipped < self.plan.offset {
+                self.skipped += 1;
+                return;
+            }
+
+            self.push_row(data);
+            self.returned += 1;
+            self.last = Some(RowKey { sort: Vec::new(), file: file.to_owned(), offset });
+        }
+    }
+
+    fn push_row(&mut self, data: &[&mut [FieldVal]]) {
+        for loc in self.plan.returning.values() {
+            self.results.push_val(&data[loc.parser][loc.field]);
+        }
+        self.results.end_row();
+    }
+
+    fn push_sorted(&mut self, file: &str, offset: u64, data: &[&mut [FieldVal]]) {
+        let key = RowKey {
+            sort: self.plan.sort.iter().map(|(loc, _)| SortVal::new(&data[loc.parser][loc.field])).collect(),
+            file: file.to_owned(),
+            offset,
+        };
+
+        if self.plan.after.as_ref().is_some_and(|after| key.cmp(after, &self.orders).is_le()) {
+            return;
+        }
+
+        self.matched += 1;
+
+        if self.threshold.as_ref().is_some_and(|t| key.cmp(t, &self.orders).is_ge()) {
+            return;
+        }
+
+        self.push_row(data);
+        self.candidates.push((key, self.candidates.len()));
+
+        if let Some(keep) = self.keep() {
+            if self.candidates.len() >= (keep * 2).max(1024) {
+                self.compact(keep);
+            }
+        }
+    }
+
+    /// Number of candidate rows that must be kept to produce the results
+    fn keep(&self) -> Option<usize> {
+        self.plan.limit.map(|limit| self.plan.offset + limit)
+    }
+
+    /// Discard all but the first `keep` candidate rows.
+    fn compact(&mut self, keep: usize) {
+        self.candidates.sort_by(|(a, _), (b, _)| a.cmp(b, &self.orders));
+        self.candidates.truncate(keep);
+        self.results = self.results.select(self.candidates.iter().map(|(_, row)| *row));
+        for (i, (_, row)) in self.candidates.iter_mut().enumerate() {
+            *row = i;
+        }
+        self.threshold = self.candidates.last().map(|(key, _)| key.clone());
+    }
+
+    pub fn finish(mut self) -> Response<ResultSet> {
+        let aggregations = self.plan.aggregations.keys().zip(std::mem::take(&mut self.aggs))
+            .map(|(name, agg)| (name.to_string(), agg.finish()))
+            .collect();
+
+        let next;
+        if self.is_sorted() {
+            self.candidates.sort_by(|(a, _), (b, _)| a.cmp(b, &self.orders));
+            let end = self.keep().unwrap_or(usize::MAX).min(self.candidates.len());
+            let page = &self.candidates[self.plan.offset.min(end)..end];
+            self.results = self.results.select(page.iter().map(|(_, row)| *row));
+            next = page.last().filter(|_| self.matched > end).map(|(key, _)| key.encode());
+        } else {
+            let more = self.more;
+            next = self.last.filter(|_| more).map(|key| key.encode());
+        }
+
+        self.stats.parse_failures = self.plan.parsers.keys().zip(&self.parse_failures)
+            .map(|(field, count)| (field.to_string(), *count))
+            .collect();
+
+        Response { stats: self.stats, results: self.results, aggregations, next }
+    }
+}
+
+#[test]
+fn test_row_key_order() {
+    let key = |sort: Vec<SortVal>, file: &str, offset| RowKey { sort, file: file.into(), offset };
+    let asc = [SortOrder::Asc];
+    let desc = [SortOrder::Desc];
+
+    let a = key(vec![SortVal::Number(2.0)], "a.log", 0);
+    let b = key(vec![SortVal::Number(10.0)], "a.log", 5);
+    let n = key(vec![SortVal::Null], "a.log", 10);
+    assert_eq!(a.cmp(&b, &asc), Ordering::Less);
+    assert_eq!(a.cmp(&b, &desc), Ordering::Greater);
+    assert_eq!(n.cmp(&a, &asc), Ordering::Greater);
+    assert_eq!(n.cmp(&a, &desc), Ordering::Greater);
+
+    // Ties are ordered by position in the scan: newer files first, then offset
+    assert_eq!(key(vec![], "a.log.2", 50).cmp(&key(vec![], "a.log.10", 0), &[]), Ordering::Greater);
+    assert_eq!(key(vec![], "a.log", 50).cmp(&key(vec![], "a.log", 60), &[]), Ordering::Less);
+
+    assert_eq!(RowKey::parse(&b.encode()).unwrap(), b);
+    assert!(RowKey::parse("garbage").is_err());
+}
+
+#[test]
+fn test_cursor_after_rotation() {
+    use std::fs;
+
+    let dir = std::env::temp_dir().join(format!("photon-test-cursor-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("a.log"), "a1\na2\na3\n").unwrap();
+    fs::write(dir.join("b.log"), "b1\nb2\n").unwrap();
+
+    let conf = toml::from_str(&format!("[source]\nsource = \"file_lines\"\npath = \"{}/*\"", dir.display())).unwrap();
+    let dataset = crate::Dataset::from_config(&conf).unwrap();
+    let query = |q: &str| dataset.query(&serde_json::from_str(q).unwrap()).unwrap();
+    fn lines(res: &Response<ResultSet>) -> Vec<crate::Value<'_>> { res.results.rows().flatten().collect() }
+    use crate::Value::String;
+
+    let page = query(r#"{"returning": ["line"], "limit": 1}"#);
+    assert_eq!(lines(&page), [String("b1")]);
+    assert_eq!((page.stats.files_opened, page.stats.files_skipped), (1, 0));
+    let after = page.next.unwrap();
+
+    // The file named by the cursor is rotated away before the next page is fetched
+    fs::rename(dir.join("b.log"), dir.join("b.log.1")).unwrap();
+    let page = query(&format!(r#"{{"returning": ["line"], "limit": 3, "after": {after:?}}}"#));
+    assert_eq!(lines(&page), [String("a1"), String("a2"), String("a3")]);
+    assert_eq!(page.next, None);
+
+    let page = query(r#"{"returning": ["line"], "limit": 5}"#);
+    assert_eq!(page.results.len(), 5);
+    assert_eq!(page.next, None);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -1,7 +1,7 @@
 use indexmap::IndexMap;
 use serde::Deserialize;
 
-use crate::{parser::{dissect::Dissect, timestamp::TimeFormat}, api::fields::FieldDisplayConfig};
+use crate::{source::{file::Multiline, path_template::PathTemplate}, parser::{dissect::Dissect, regex::RegexPattern, grok::Grok, kv::KeyValue, csv::Csv, geoip::GeoIp, timestamp::TimeFormat, units::{Duration, Bytes}}, api::fields::FieldDisplayConfig};
 
 #[derive(Clone, Deserialize)]
 pub struct Dataset {
@@ -22,7 +22,36 @@ pub struct Source {
 #[serde(tag = "source")]
 #[serde(rename_all = "snake_case")]
 pub enum SourceKind {   
-    FileLines { path: String }
+    FileLines {
+        path: String,
+
+        #[serde(default)]
+        multiline: Option<Multiline>, // { start = '^\d{4}-', indented = true, max_lines = 500 }
+
+        /// Glob selecting the members of tar and zip archives to read, by default all files
+        #[serde(default)]
+        members: Option<String>,
+
+        /// Fields extracted from the path of each file, like `/logs/{service}/{date}/{host}.log`
+        #[serde(default)]
+        path_template: Option<PathTemplate>,
+    },
+    Stdin {
+        #[serde(default)]
+        multiline: Option<Multiline>,
+    },
+}
+
+impl SourceKind {
+    /// A source reading standard input, with the same framing of records as
+    /// this one. Fields from a path template are not extracted, so are null.
+    pub fn stdin(&self) -> SourceKind {
+        match self {
+            SourceKind::FileLines { multiline, .. } | SourceKind::Stdin { multiline } => {
+                SourceKind::Stdin { multiline: multiline.clone() }
+            }
+        }
+    }
 }
 
 #[non_exhaustive]
@@ -32,7 +61,10 @@ pub enum SourceKind {
 pub enum ParserKind {
     Keyword,
     Number,
+    Bool,
     Dissect { pattern: Dissect },
+    Regex { pattern: RegexPattern },
+    Grok(Grok), // { pattern = "...", patterns = { NAME = "..." } }
     UserAgent,
     Timestamp {
         format: TimeFormat,
@@ -40,15 +72,52 @@ pub enum ParserKind {
         assume_utc: bool,
     },
     Json,
+    Logfmt,
+    Kv(KeyValue), // { pair_separator = " ", value_separator = "=", quote = '"' }
+    Csv(Csv), // { delimiter = ",", quote = '"', columns = [...] } or { header = true }
+    Syslog,
+    Url,
+    Ip,
+    GeoIp(GeoIp), // { database = "GeoLite2-City.mmdb" }
+    Duration(Duration), // { assume_unit = "ms" }, in seconds
+    Bytes(Bytes), // { assume_unit = "KiB" }, in bytes
+
+    // Presets for common formats, which also parse their child fields
+    #[serde(rename = "apache_common")]
+    ApacheCommon,
+    #[serde(rename = "apache_combined")]
+    ApacheCombined,
+    #[serde(rename = "apache_error")]
+    ApacheError,
+    #[serde(rename = "nginx_combined")]
+    NginxCombined,
+    #[serde(rename = "nginx_error")]
+    NginxError,
 }
 
 
 #[derive(Clone, Deserialize)]
-pub struct Field {    
-    #[serde(flatten)]
+#[serde(try_from = "toml::value::Table")]
+pub struct Field {
     pub parser: Option<ParserKind>,
-
-    #[serde(flatten)]
     pub display: FieldDisplayConfig,
 }
 
+/// The parser and display options share a table. They are split explicitly
+/// rather than with `#[serde(flatten)]`, which would ignore errors in the
+/// parser options and pass the display options to the parser.
+impl TryFrom<toml::value::Table> for Field {
+    type Error = toml::de::Error;
+
+    fn try_from(mut table: toml::value::Table) -> Result<Self, Self::Error> {
+        let display: toml::value::Table = FieldDisplayConfig::KEYS.iter()
+            .filter_map(|&key| Some((key.to_owned(), table.remove(key)?)))
+            .collect();
+
+        Ok(Field {
+            parser: if table.is_empty() { None } else { Some(toml::Value::Table(table).try_into()?) },
+            display: toml::Value::Table(display).try_into()?,
+        })
+    }
+}
+
@@ -1,11 +1,71 @@
+use std::net::IpAddr;
+
+use regex::{Regex, RegexBuilder};
 use time::OffsetDateTime;
 use time::ext::NumericalDuration;
 
-use crate::{api::query::QueryFilter, query::FieldVal};
+use crate::{api::query::QueryFilter, query::{FieldVal, FieldRef, FilterPlan, QueryError}};
+
+/// A `QueryFilter` prepared for evaluation against many values
+pub(crate) struct FieldFilter {
+    filter: QueryFilter,
+    regex: Option<Regex>,
+
+    /// Values of an `is` or `not` filter that are IP addresses, to compare against IP fields
+    ips: Vec<IpAddr>,
+}
+
+impl FieldFilter {
+    pub fn new(filter: &QueryFilter) -> Result<FieldFilter, QueryError> {
+        let regex = match filter {
+            QueryFilter::Regex { regex, case_insensitive } => {
+                Some(RegexBuilder::new(regex).case_insensitive(*case_insensitive).build()?)
+            }
+            _ => None,
+        };
+        let ips = match filter {
+            QueryFilter::KeywordIs { is: values } | QueryFilter::KeywordNot { not: values } => {
+                values.iter().filter_map(|s| s.parse().ok()).collect()
+            }
+            _ => Vec::new(),
+        };
+        Ok(FieldFilter { filter: filter.clone(), regex, ips })
+    }
+}
+
+pub(crate) fn filter_matches(filter: &FilterPlan, data: &[&mut [FieldVal]]) -> bool {
+    match filter {
+        FilterPlan::Field(loc, filter) => filter_test(filter, &data[loc.parser][loc.field]),
+        FilterPlan::And(all) => all.iter().all(|f| filter_matches(f, data)),
+        FilterPlan::Or(any) => any.iter().any(|f| filter_matches(f, data)),
+        FilterPlan::Not(f) => !filter_matches(f, data),
+    }
+}
+
+/// Evaluate a filter where only some fields are known, returning `None` if
+/// the result depends on the unknown fields.
+pub(crate) fn filter_partial(filter: &FilterPlan, data: &[&mut [FieldVal]], known: &dyn Fn(FieldRef) -> bool) -> Option<bool> {
+    match filter {
+        FilterPlan::Field(loc, filter) => known(*loc).then(|| filter_test(filter, &data[loc.parser][loc.field])),
+        FilterPlan::And(all) => {
+            let results: Vec<_> = all.iter().map(|f| filter_partial(f, data, known)).collect();
+            if results.contains(&Some(false)) { Some(false) } else if results.contains(&None) { None } else { Some(true) }
+        }
+        FilterPlan::Or(any) => {
+            let results: Vec<_> = any.iter().map(|f| filter_partial(f, data, known)).collect();
+            if results.contains(&Some(true)) { Some(true) } else if results.contains(&None) { None } else { Some(false) }
+        }
+        FilterPlan::Not(f) => filter_partial(f, data, known).map(|r| !r),
+    }
+}
 
-pub(crate) fn filter_test(filter: &QueryFilter, val: &FieldVal) -> bool{
-    match (filter, val) {
+pub(crate) fn filter_test(filter: &FieldFilter, val: &FieldVal) -> bool{
+    match (&filter.filter, val) {
         (QueryFilter::Present { present }, v) => v.exists() == *present,
+
+        // Multi-valued fields match if any element matches, or for `not`, if no element is excluded
+        (QueryFilter::KeywordNot { .. }, FieldVal::Array(items)) => items.iter().all(|v| filter_test(filter, v)),
+        (_, FieldVal::Array(items)) => items.iter().any(|v| filter_test(filter, v)),
         
         (QueryFilter::Range { min, max }, FieldVal::Number(n)) => 
             *n >= min.unwrap_or(f64::NEG_INFINITY) && *n <= max.unwrap_or(f64::INFINITY),
@@ -17,9 +77,131 @@ pub(crate) fn filter_test(filter: &QueryFilter, val: &FieldVal) -> bool{
         (QueryFilter::TimeSince { since }, FieldVal::Time(t)) => t > &(OffsetDateTime::now_utc() - since.seconds()),
         (QueryFilter::TimeSince { .. }, _) => false,
         
+        (QueryFilter::Cidr { cidr }, FieldVal::Ip(ip)) => cidr.iter().any(|net| net.contains(*ip)),
+        (QueryFilter::Cidr { cidr }, FieldVal::String(s)) => s.parse().is_ok_and(|ip| cidr.iter().any(|net| net.contains(ip))),
+        (QueryFilter::Cidr { .. }, _) => false,
+
+        (QueryFilter::IsTrue { is_true }, FieldVal::Bool(b)) => b == is_true,
+        (QueryFilter::IsTrue { .. }, _) => false,
+
+        (QueryFilter::Contains { contains, case_insensitive: false }, FieldVal::String(s)) => s.contains(&contains[..]),
+        (QueryFilter::Contains { contains, case_insensitive: true }, FieldVal::String(s)) => contains_ignore_case(s, contains),
+        (QueryFilter::Prefix { prefix }, FieldVal::String(s)) => s.starts_with(&prefix[..]),
+        (QueryFilter::Suffix { suffix }, FieldVal::String(s)) => s.ends_with(&suffix[..]),
+        (QueryFilter::Match { r#match }, FieldVal::String(s)) => words(r#match).all(|w| words(s).any(|v| eq_ignore_case(v, w))),
+        (QueryFilter::Regex { .. }, FieldVal::String(s)) => filter.regex.as_ref().is_some_and(|re| re.is_match(s)),
+        (QueryFilter::Contains{..} | QueryFilter::Prefix{..} | QueryFilter::Suffix{..} | QueryFilter::Match{..} | QueryFilter::Regex{..}, _) => false,
+
         (QueryFilter::KeywordIs { is }, FieldVal::String(s)) => is.contains(*s),
         (QueryFilter::KeywordNot { not }, FieldVal::String(s)) => !not.contains(*s),
+        (QueryFilter::KeywordIs { .. }, FieldVal::Ip(ip)) => filter.ips.contains(ip),
+        (QueryFilter::KeywordNot { .. }, FieldVal::Ip(ip)) => !filter.ips.contains(ip),
+        (QueryFilter::KeywordIs { is }, FieldVal::Bool(b)) => is.contains(if *b { "true" } else { "false" }),
+        (QueryFilter::KeywordNot { not }, FieldVal::Bool(b)) => !not.contains(if *b { "true" } else { "false" }),
         (QueryFilter::KeywordIs{..} | QueryFilter::KeywordNot{..}, _) => false,
     }
 }
 
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    if needle.is_ascii() {
+        // Non-ASCII bytes in the haystack can't match any byte of an ASCII needle, so byte-wise comparison is sufficient
+        needle.is_empty() || haystack.as_bytes().windows(needle.len()).any(|w| w.eq_ignore_ascii_case(needle.as_bytes()))
+    } else {
+        haystack.to_lowercase().contains(&needle.to_lowercase())
+    }
+}
+
+fn eq_ignore_case(a: &str, b: &str) -> bool {
+    a.chars().flat_map(char::to_lowercase).eq(b.chars().flat_map(char::to_lowercase))
+}
+
+fn words(s: &str) -> impl Iterator<Item = &str> {
+    s.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty())
+}
+
+#[cfg(test)]
+fn check(json: &str, val: FieldVal) -> bool {
+    filter_test(&FieldFilter::new(&serde_json::from_str(json).unwrap()).unwrap(), &val)
+}
+
+#[test]
+fn test_text_filters() {
+    let val = FieldVal::String("ERROR: Connection refused (os error 111)");
+
+    assert!(check(r#"{"contains": "Connection refused"}"#, val));
+    assert!(!check(r#"{"contains": "connection refused"}"#, val));
+    assert!(check(r#"{"contains": "connection REFUSED", "case_insensitive": true}"#, val));
+    assert!(check(r#"{"prefix": "ERROR:"}"#, val));
+    assert!(!check(r#"{"prefix": "WARN"}"#, val));
+    assert!(check(r#"{"suffix": "111)"}"#, val));
+    assert!(check(r#"{"match": "refused error"}"#, val));
+    assert!(!check(r#"{"match": "refuse"}"#, val));
+    assert!(check(r#"{"regex": "^ERROR: .* \\(os error \\d+\\)$"}"#, val));
+    assert!(!check(r#"{"regex": "^error"}"#, val));
+    assert!(check(r#"{"regex": "^error", "case_insensitive": true}"#, val));
+    assert!(FieldFilter::new(&QueryFilter::Regex { regex: "(".into(), case_insensitive: false }).is_err());
+    assert!(!check(r#"{"contains": "1"}"#, FieldVal::Number(1.0)));
+    assert!(contains_ignore_case("ÜBER alles", "über"));
+}
+
+#[test]
+fn test_ip_filters() {
+    let ip = FieldVal::Ip("10.1.2.3".parse().unwrap());
+
+    assert!(check(r#"{"cidr": ["192.168.0.0/16", "10.0.0.0/8"]}"#, ip));
+    assert!(!check(r#"{"cidr": ["192.168.0.0/16"]}"#, ip));
+    assert!(check(r#"{"cidr": ["10.0.0.0/8"]}"#, FieldVal::String("10.9.9.9")));
+    assert!(!check(r#"{"cidr": ["10.0.0.0/8"]}"#, FieldVal::String("10.9.9.9.example.com")));
+    assert!(check(r#"{"is": ["10.1.2.3"]}"#, ip));
+    assert!(check(r#"{"is": ["::1"]}"#, FieldVal::Ip("0::0001".parse().unwrap())));
+    assert!(check(r#"{"not": ["10.1.2.4"]}"#, ip));
+    assert!(!check(r#"{"not": ["web", "10.1.2.3"]}"#, ip));
+    assert!(!check(r#"{"is": ["web", "10.1.2.30"]}"#, ip));
+}
+
+#[test]
+fn test_bool_filters() {
+    assert!(check(r#"{"is_true": true}"#, FieldVal::Bool(true)));
+    assert!(!check(r#"{"is_true": true}"#, FieldVal::Bool(false)));
+    assert!(check(r#"{"is_true": false}"#, FieldVal::Bool(false)));
+    assert!(!check(r#"{"is_true": false}"#, FieldVal::Null));
+    assert!(!check(r#"{"is_true": true}"#, FieldVal::String("true")));
+    assert!(check(r#"{"is": ["true"]}"#, FieldVal::Bool(true)));
+    assert!(check(r#"{"not": ["true"]}"#, FieldVal::Bool(false)));
+}
+
+#[test]
+fn test_array_filters() {
+    let tags = FieldVal::Array(&[FieldVal::String("prod"), FieldVal::String("eu-west")]);
+
+    assert!(check(r#"{"is": ["prod"]}"#, tags));
+    assert!(!check(r#"{"is": ["dev"]}"#, tags));
+    assert!(!check(r#"{"not": ["prod"]}"#, tags));
+    assert!(check(r#"{"not": ["dev"]}"#, tags));
+    assert!(check(r#"{"prefix": "eu-"}"#, tags));
+    assert!(check(r#"{"present": true}"#, tags));
+    assert!(!check(r#"{"is": ["prod"]}"#, FieldVal::Array(&[])));
+    assert!(check(r#"{"min": 2}"#, FieldVal::Array(&[FieldVal::Number(1.0), FieldVal::Number(3.0)])));
+}
+
+#[test]
+fn test_filter_partial() {
+    let field = |field, json: &str| FilterPlan::Field(FieldRef { parser: 0, field }, FieldFilter::new(&serde_json::from_str(json).unwrap()).unwrap());
+    let known = field(0, r#"{"is": ["api"]}"#);
+    let unknown = || field(1, r#"{"is": ["x"]}"#);
+
+    let test = |service, filter: &FilterPlan| {
+        let mut root = [FieldVal::String(service), FieldVal::Null];
+        filter_partial(filter, &[&mut root[..]], &|loc| loc.field == 0)
+    };
+
+    assert_eq!(test("api", &known), Some(true));
+    assert_eq!(test("db", &known), Some(false));
+    assert_eq!(test("db", &unknown()), None);
+    assert_eq!(test("db", &FilterPlan::And(vec![unknown(), field(0, r#"{"is": ["api"]}"#)])), Some(false));
+    assert_eq!(test("api", &FilterPlan::And(vec![unknown(), field(0, r#"{"is": ["api"]}"#)])), None);
+    assert_eq!(test("api", &FilterPlan::Or(vec![unknown(), field(0, r#"{"is": ["api"]}"#)])), Some(true));
+    assert_eq!(test("db", &FilterPlan::Or(vec![unknown(), field(0, r#"{"is": ["api"]}"#)])), None);
+    assert_eq!(test("db", &FilterPlan::Not(Box::new(known))), Some(true));
+    assert_eq!(test("db", &FilterPlan::Not(Box::new(unknown()))), None);
+}
@@ -1,4 +1,4 @@
-use std::{fs, path::{Path, PathBuf}, io};
+use std::{fs, path::{Path, PathBuf}, io, time::Instant};
 use api::fields::{FieldType, FieldDisplayConfig};
 use config::dataset::ParserKind;
 use indexmap::IndexMap;
@@ -9,15 +9,18 @@ mod source;
 mod parser;
 mod filter;
 mod query;
+mod aggregate;
+mod collect;
 mod resultset;
 
-use source::Source;
 use thiserror::Error;
 
 use query::QueryPlan;
-pub use resultset::ResultSet;
+pub use resultset::{ResultSet, Value};
 pub use query::QueryError;
 
+const DATASET_SUFFIX: &str = ".dataset.toml";
+
 pub struct Config {
     config_dir: PathBuf,
     datasets: IndexMap<String, Result<Dataset, ConfigError>>,
@@ -34,7 +37,7 @@ impl Config {
         self.datasets = fs::read_dir(&self.config_dir)?
             .filter_map(|f| f.ok())
             .filter_map(|f| {
-                if let Some(name) = f.file_name().to_str().and_then(|name| name.strip_suffix(".dataset.toml")) {
+                if let Some(name) = f.file_name().to_str().and_then(|name| name.strip_suffix(DATASET_SUFFIX)) {
                     let dataset = Dataset::from_config_file(f.path());
                     
                     if let Err(e) = &dataset {
@@ -58,6 +61,32 @@ impl Config {
     pub fn dataset(&self, name: &str) -> Option<Result<&Dataset, &ConfigError>> {
         self.datasets.get(name).map(|x| x.as_ref())
     }
+
+    /// Load only the named dataset, reading records from standard input
+    /// instead of its configured source.
+    pub fn load_dataset_stdin(config_dir: &Path, name: &str) -> Option<Result<Dataset, ConfigError>> {
+        let fname = config_dir.join(format!("{name}{DATASET_SUFFIX}"));
+        if !fname.is_file() { return None }
+
+        let dataset = read_dataset_config(&fname).and_then(|mut config| {
+            if let config::dataset::SourceKind::FileLines { path_template: Some(template), .. } = &config.source.kind {
+                let names = template.names().collect::<Vec<_>>().join(", ");
+                eprintln!("Fields from the path template of dataset `{name}` are null when reading standard input: {names}");
+            }
+            config.source.kind = config.source.kind.stdin();
+            Dataset::from_config(&config)
+        });
+
+        if let Err(e) = &dataset {
+            eprintln!("Configuration error for dataset `{name}`: {e}")
+        }
+        Some(dataset)
+    }
+}
+
+fn read_dataset_config(fname: &Path) -> Result<config::dataset::Dataset, ConfigError> {
+    let data = fs::read(fname)?;
+    Ok(toml::from_slice(&data)?)
 }
 
 pub (crate) struct FieldDefaults {
@@ -79,6 +108,14 @@ impl Field {
     fn apply_defaults(&mut self, d: &FieldDefaults) {
         self.default_ty = Some(d.ty);
     }
+
+    fn display(&self) -> FieldDisplayConfig {
+        let mut display = self.display.clone();
+        if display.unit.is_none() {
+            display.unit = self.parser.as_ref().and_then(parser::unit).map(str::to_owned);
+        }
+        display
+    }
 }
 
 pub struct Dataset {
@@ -111,6 +148,14 @@ impl Dataset {
             for (child_field, defaults) in child_fields {
                 fields.entry(format!("{field_name}/{child_field}")).or_default().apply_defaults(&defaults);
             }
+
+            // Presets also specify parsers for their children, unless configured explicitly
+            if let Some(preset) = conf_field.parser.as_ref().and_then(parser::preset::get) {
+                for (child_field, parser) in &preset.children {
+                    let field = fields.entry(format!("{field_name}/{child_field}")).or_default();
+                    field.parser.get_or_insert_with(|| parser.clone());
+                }
+            }
         }
 
         fields.sort_keys();
@@ -119,19 +164,20 @@ impl Dataset {
     }
 
     pub fn from_config_file(fname: impl AsRef<Path>) -> Result<Dataset, ConfigError> {
-        let data = fs::read(fname)?;
-        let config = toml::from_slice(&data)?;
-        Self::from_config(&config)
+        Self::from_config(&read_dataset_config(fname.as_ref())?)
     }
 
-    pub fn query(&self, q: &api::query::Query) -> Result<ResultSet, QueryError> {
-        let plan = QueryPlan::new(&self, q)?;
-        self.source.query(plan)
+    pub fn query(&self, q: &api::query::Query) -> Result<api::query::Response<ResultSet>, QueryError> {
+        let start = Instant::now();
+        let plan = QueryPlan::new(self, q)?;
+        let mut response = self.source.query(plan)?;
+        response.stats.elapsed = start.elapsed().as_secs_f64();
+        Ok(response)
     }
 
     pub fn fields(&self) -> api::fields::Fields {
         let fields = self.fields.iter().map(|(k, field)| {
-            (k.to_owned(), api::fields::Field { ty: field.ty(), display: field.display.clone() })
+            (k.to_owned(), api::fields::Field { ty: field.ty(), display: field.display() })
         }).collect();
         api::fields::Fields { fields }
     }
@@ -1,6 +1,6 @@
 use clap::Parser;
 use photon::Config;
-use std::{convert::Infallible, net::SocketAddr, sync::Arc, io};
+use std::{convert::Infallible, net::SocketAddr, sync::Arc, io, process};
 use tokio::sync::RwLock;
 
 mod server;
@@ -27,6 +27,11 @@ enum Args {
 
         #[arg(short, long)]
         query: String,
+
+        /// Read lines from standard input instead of the dataset's source.
+        /// Fields from a path template are null.
+        #[arg(long)]
+        stdin: bool,
     },
 }
 
@@ -58,16 +63,33 @@ async fn main() {
         }
         Args::Query {
             config_dir,
-            dataset,
+            dataset: name,
             query,
+            stdin,
         } => {
-            let config = Config::load(config_dir).unwrap();
-            let dataset = config.dataset(&dataset).expect("dataset does not exist").expect("config error");
+            let (config, piped);
+            let dataset = if stdin {
+                piped = Config::load_dataset_stdin(&config_dir, &name);
+                piped.as_ref().map(|d| d.as_ref())
+            } else {
+                config = Config::load(config_dir).unwrap();
+                config.dataset(&name)
+            };
+
+            // Configuration errors were already reported while loading
+            let dataset = match dataset {
+                Some(Ok(dataset)) => dataset,
+                Some(Err(_)) => process::exit(1),
+                None => {
+                    eprintln!("Dataset `{name}` does not exist");
+                    process::exit(1)
+                }
+            };
 
             let query = serde_json::from_str(&query).expect("failed to parse query");
-            let results = dataset.query(&query).expect("failed to run query");
+            let response = dataset.query(&query).expect("failed to run query");
 
-            serde_json::to_writer(io::stdout().lock(), &results).expect("failed to write output");
+            serde_json::to_writer(io::stdout().lock(), &response).expect("failed to write output");
         }
     }
 }
@@ -9,14 +9,13 @@ impl ParserInst for KeywordInst {
         None
     }
 
-    fn parse<'b>(&self, bump: &'b Bump, input: &mut FieldVal<'b>) -> &'b mut [FieldVal<'b>] {
+    fn parse<'b>(&self, bump: &'b Bump, input: &mut FieldVal<'b>) -> Option<&'b mut [FieldVal<'b>]> {
         match input {
-            FieldVal::Number(n) => {
-                *input = FieldVal::String(bumpalo::format!(in bump, "{}", n).into_bump_str());
-            }
+            FieldVal::Number(n) => *input = FieldVal::String(bumpalo::format!(in bump, "{}", n).into_bump_str()),
+            FieldVal::Bool(b) => *input = FieldVal::String(if *b { "true" } else { "false" }),
             _ => {}
         }
-        &mut []
+        Some(&mut [])
     }
 }
 
@@ -27,15 +26,66 @@ impl ParserInst for NumberInst {
         None
     }
 
-    fn parse<'b>(&self, _bump: &'b Bump, input: &mut FieldVal<'b>) -> &'b mut [FieldVal<'b>] {
+    fn parse<'b>(&self, _bump: &'b Bump, input: &mut FieldVal<'b>) -> Option<&'b mut [FieldVal<'b>]> {
+        if let FieldVal::String(n) = input {
+            *input = FieldVal::Number(n.parse().ok()?);
+        }
+        Some(&mut [])
+    }
+}
+
+pub(crate) struct BoolInst;
+
+fn parse_bool(s: &str) -> Option<bool> {
+    const TRUE: &[&str] = &["true", "t", "yes", "y", "1", "on"];
+    const FALSE: &[&str] = &["false", "f", "no", "n", "0", "off"];
+
+    let s = s.trim();
+    if TRUE.iter().any(|t| t.eq_ignore_ascii_case(s)) {
+        Some(true)
+    } else if FALSE.iter().any(|f| f.eq_ignore_ascii_case(s)) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+impl ParserInst for BoolInst {
+    fn require_field(&mut self, _field: &str) -> Option<usize> {
+        None
+    }
+
+    fn parse<'b>(&self, _bump: &'b Bump, input: &mut FieldVal<'b>) -> Option<&'b mut [FieldVal<'b>]> {
         match input {
-            FieldVal::String(n) => {
-                if let Ok(n) = n.parse() {
-                    *input = FieldVal::Number(n);
-                }
-            }
-            _ => {}
+            FieldVal::String(s) => *input = FieldVal::Bool(parse_bool(s)?),
+            FieldVal::Number(n) if *n == 0.0 || *n == 1.0 => *input = FieldVal::Bool(*n == 1.0),
+            FieldVal::Bool(_) => {}
+            _ => return None,
         }
-        &mut []
+        Some(&mut [])
     }
 }
+
+#[test]
+fn test() {
+    let bump = Bump::new();
+    let cast = |s: &'static str| {
+        let mut input = FieldVal::String(s);
+        BoolInst.parse(&bump, &mut input).map(|_| input)
+    };
+
+    assert_eq!(cast("Yes"), Some(FieldVal::Bool(true)));
+    assert_eq!(cast("on"), Some(FieldVal::Bool(true)));
+    assert_eq!(cast(" 0 "), Some(FieldVal::Bool(false)));
+    assert_eq!(cast("FALSE"), Some(FieldVal::Bool(false)));
+    assert_eq!(cast("maybe"), None);
+
+    let mut input = FieldVal::Number(1.0);
+    BoolInst.parse(&bump, &mut input).unwrap();
+    assert_eq!(input, FieldVal::Bool(true));
+    assert!(BoolInst.parse(&bump, &mut FieldVal::Number(2.0)).is_none());
+
+    let mut input = FieldVal::Bool(true);
+    KeywordInst.parse(&bump, &mut input).unwrap();
+    assert_eq!(input, FieldVal::String("true"));
+}
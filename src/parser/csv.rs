@@ -0,0 +1,226 @@
+use std::cell::RefCell;
+
+use bumpalo::Bump;
+use bumpalo::collections::String as BString;
+use indexmap::IndexSet;
+use serde::Deserialize;
+
+use crate::{query::FieldVal, FieldDefaults, api::fields::FieldType};
+
+use super::ParserInst;
+
+#[derive(Clone, Deserialize)]
+pub struct Csv {
+    #[serde(default = "default_delimiter")]
+    delimiter: char,
+
+    /// Character surrounding values that contain delimiters. Within a quoted
+    /// value, a doubled quote is a literal quote unless `escape` is set.
+    #[serde(default = "default_quote")]
+    quote: Option<char>,
+
+    /// Character that causes the following character to be taken literally
+    #[serde(default)]
+    escape: Option<char>,
+
+    /// Column names. If empty, columns are named by their index starting from 0.
+    #[serde(default)]
+    columns: Vec<String>,
+
+    /// Take the column names from the first line of each file instead of `columns`
+    #[serde(default)]
+    header: bool,
+}
+
+fn default_delimiter() -> char { ',' }
+fn default_quote() -> Option<char> { Some('"') }
+
+impl Csv {
+    /// Split a record into values, returning `false` if it has mismatched quotes.
+    fn split<'b>(&self, bump: &'b Bump, s: &'b str, mut push: impl FnMut(&'b str)) -> bool {
+        let mut rest = s.strip_suffix('\r').unwrap_or(s);
+
+        loop {
+            let value;
+            if let Some(quote) = self.quote.filter(|&q| rest.starts_with(q)) {
+                let Some((v, after)) = self.read_quoted(bump, &rest[quote.len_utf8()..], quote) else { return false };
+                if !(after.is_empty() || after.starts_with(self.delimiter)) { return false }
+                (value, rest) = (v, after);
+            } else {
+                (value, rest) = self.read_unquoted(bump, rest);
+            }
+
+            push(value);
+
+            match rest.strip_prefix(self.delimiter) {
+                Some(r) => rest = r,
+                None => return true,
+            }
+        }
+    }
+
+    fn read_unquoted<'b>(&self, bump: &'b Bump, s: &'b str) -> (&'b str, &'b str) {
+        let end = s.find(self.delimiter).unwrap_or(s.len());
+
+        match self.escape {
+            Some(escape) if s[..end].contains(escape) => {
+                let mut value = BString::new_in(bump);
+                let mut chars = s.char_indices();
+                while let Some((i, c)) = chars.next() {
+                    if c == escape {
+                        if let Some((_, c)) = chars.next() { value.push(c) }
+                    } else if c == self.delimiter {
+                        return (value.into_bump_str(), &s[i..]);
+                    } else {
+                        value.push(c);
+                    }
+                }
+                (value.into_bump_str(), "")
+            }
+            _ => (&s[..end], &s[end..]),
+        }
+    }
+
+    /// Read a value following an opening quote, returning the value and the input following the closing quote.
+    fn read_quoted<'b>(&self, bump: &'b Bump, s: &'b str, quote: char) -> Option<(&'b str, &'b str)> {
+        let escape = self.escape.filter(|&e| e != quote);
+        let is_doubled = |rest: &str| escape.is_none() && rest.starts_with(quote);
+
+        let first = s.find(|c| c == quote || Some(c) == escape)?;
+        let after_first = &s[first + quote.len_utf8()..];
+        if s[first..].starts_with(quote) && !is_doubled(after_first) {
+            return Some((&s[..first], after_first));
+        }
+
+        let mut value = BString::from_str_in(&s[..first], bump);
+        let mut chars = s[first..].char_indices().map(|(i, c)| (first + i, c));
+        while let Some((i, c)) = chars.next() {
+            if Some(c) == escape {
+                value.push(chars.next()?.1);
+            } else if c == quote {
+                let after = &s[i + quote.len_utf8()..];
+                if is_doubled(after) {
+                    value.push(quote);
+                    chars.next();
+                } else {
+                    return Some((value.into_bump_str(), after));
+                }
+            } else {
+                value.push(c);
+            }
+        }
+        None
+    }
+
+    #[cfg(test)]
+    fn parse<'b>(&self, bump: &'b Bump, s: &'b str) -> Option<Vec<&'b str>> {
+        let mut values = Vec::new();
+        self.split(bump, s, |v| values.push(v)).then_some(values)
+    }
+}
+
+pub(crate) fn fields(s: &Csv) -> Vec<(&str, FieldDefaults)> {
+    if s.header { return vec![] }
+    s.columns.iter().map(|x| (&x[..], FieldDefaults { ty: FieldType::Keyword })).collect()
+}
+
+pub(crate) struct CsvInst<'req> {
+    config: &'req Csv,
+    fields: IndexSet<String>,
+
+    /// Column index of each required field in the current file
+    columns: RefCell<Vec<Option<usize>>>,
+}
+
+impl<'req> CsvInst<'req> {
+    pub(crate) fn new(config: &'req Csv) -> Self {
+        CsvInst { config, fields: IndexSet::new(), columns: RefCell::new(Vec::new()) }
+    }
+}
+
+impl<'req> ParserInst for CsvInst<'req> {
+    fn require_field(&mut self, field: &str) -> Option<usize> {
+        if let Some(i) = self.fields.get_index_of(field) {
+            return Some(i);
+        }
+
+        let column = if self.config.header {
+            None // determined by each file's header
+        } else if self.config.columns.is_empty() {
+            Some(field.parse().ok()?)
+        } else {
+            Some(self.config.columns.iter().position(|c| c == field)?)
+        };
+
+        self.columns.get_mut().push(column);
+        Some(self.fields.insert_full(field.to_owned()).0)
+    }
+
+    fn file_header<'b>(&self, bump: &'b Bump, input: &FieldVal<'b>) -> bool {
+        if !self.config.header { return false }
+
+        let mut names = Vec::new();
+        if let FieldVal::String(s) = *input {
+            self.config.split(bump, s, |name| names.push(name));
+        }
+
+        *self.columns.borrow_mut() = self.fields.iter()
+            .map(|field| names.iter().position(|name| name == field))
+            .collect();
+        true
+    }
+
+    fn parse<'b>(&self, bump: &'b Bump, input: &mut FieldVal<'b>) -> Option<&'b mut [FieldVal<'b>]> {
+        let FieldVal::String(s) = *input else { return None };
+
+        let mut values = bumpalo::collections::Vec::new_in(bump);
+        if !self.config.split(bump, s, |v| values.push(v)) {
+            return None;
+        }
+
+        let columns = self.columns.borrow();
+        Some(bump.alloc_slice_fill_iter(columns.iter().map(|column| {
+            column.and_then(|c| values.get(c)).map_or(FieldVal::Null, |v| FieldVal::String(v))
+        })))
+    }
+}
+
+#[test]
+fn test() {
+    let bump = Bump::new();
+    let csv = |toml_src: &str| toml::from_str::<Csv>(toml_src).unwrap();
+
+    let default = csv("");
+    assert_eq!(default.parse(&bump, "a,b,c"), Some(vec!["a", "b", "c"]));
+    assert_eq!(default.parse(&bump, "a,,c,\r"), Some(vec!["a", "", "c", ""]));
+    assert_eq!(default.parse(&bump, r#"1,"hello, world","say ""hi""""#), Some(vec!["1", "hello, world", r#"say "hi""#]));
+    assert_eq!(default.parse(&bump, r#"1,"unterminated"#), None);
+    assert_eq!(default.parse(&bump, r#"1,"a"b,c"#), None);
+    assert_eq!(default.parse(&bump, r#"x"y,z"#), Some(vec![r#"x"y"#, "z"]));
+
+    let tsv = csv(r#"delimiter = "\t"
+        quote = "'"
+        escape = "\\""#);
+    assert_eq!(tsv.parse(&bump, "a\t'b\tc'\td\\\te\t'it\\'s'"), Some(vec!["a", "b\tc", "d\te", "it's"]));
+
+    let header = csv("header = true");
+    let mut inst = CsvInst::new(&header);
+    assert_eq!(inst.require_field("user"), Some(0));
+    assert_eq!(inst.require_field("missing"), Some(1));
+    assert!(inst.file_header(&bump, &FieldVal::String("time,user,action")));
+    assert_eq!(inst.parse(&bump, &mut FieldVal::String("12:00,bob,login")).unwrap(), &[FieldVal::String("bob"), FieldVal::Null]);
+    assert!(inst.file_header(&bump, &FieldVal::String("user,time")));
+    assert_eq!(inst.parse(&bump, &mut FieldVal::String("alice,13:00")).unwrap(), &[FieldVal::String("alice"), FieldVal::Null]);
+
+    let named = csv(r#"columns = ["time", "user"]"#);
+    let mut inst = CsvInst::new(&named);
+    assert_eq!(inst.require_field("user"), Some(0));
+    assert_eq!(inst.require_field("nope"), None);
+    assert!(!inst.file_header(&bump, &FieldVal::String("time,user")));
+    assert_eq!(inst.parse(&bump, &mut FieldVal::String("12:00,bob")).unwrap(), &[FieldVal::String("bob")]);
+
+    let mut inst = CsvInst::new(&default);
+    assert_eq!(inst.require_field("2"), Some(0));
+    assert_eq!(inst.require_field("x"), None);
+    assert_eq!(inst.parse(&bump, &mut FieldVal::String("a,b,c")).unwrap(), &[FieldVal::String("c")]);
+}
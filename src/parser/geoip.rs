@@ -0,0 +1,268 @@
+use std::{cell::RefCell, collections::{BTreeMap, HashMap}, net::IpAddr, path::PathBuf, sync::Arc};
+
+use bumpalo::Bump;
+use maxminddb::Reader;
+use serde::Deserialize;
+
+use crate::{query::FieldVal, FieldDefaults, api::fields::FieldType};
+
+use super::ParserInst;
+
+const FIELDS: &[(&str, FieldType)] = &[
+    ("country", FieldType::Keyword),
+    ("country_name", FieldType::Keyword),
+    ("city", FieldType::Keyword),
+    ("latitude", FieldType::Number),
+    ("longitude", FieldType::Number),
+    ("asn", FieldType::Number),
+    ("as_org", FieldType::Keyword),
+];
+
+/// Maximum number of addresses cached by a query. When it is reached, the
+/// whole cache is cleared rather than evicting the least recently used
+/// entries, which is simpler and only costs repeated lookups.
+const CACHE_SIZE: usize = 65536;
+
+pub(crate) fn fields() -> Vec<(&'static str, FieldDefaults)> {
+    super::typed_fields(FIELDS)
+}
+
+/// A MaxMind DB file, such as GeoLite2 City or ASN, loaded with the dataset config.
+#[derive(Clone, Deserialize)]
+#[serde(try_from = "GeoIpConfig")]
+pub struct GeoIp {
+    reader: Arc<Reader<Vec<u8>>>,
+}
+
+#[derive(Deserialize)]
+struct GeoIpConfig {
+    /// Absolute path, because a relative path would depend on the server's working directory
+    database: PathBuf,
+}
+
+impl TryFrom<GeoIpConfig> for GeoIp {
+    type Error = String;
+
+    fn try_from(config: GeoIpConfig) -> Result<Self, Self::Error> {
+        if config.database.is_relative() {
+            return Err(format!("GeoIP database path must be absolute: {}", config.database.display()));
+        }
+        let reader = Reader::open_readfile(&config.database)
+            .map_err(|e| format!("{}: {e}", config.database.display()))?;
+        Ok(GeoIp { reader: Arc::new(reader) })
+    }
+}
+
+/// The subset of the GeoIP2 City and GeoLite2 ASN record formats that is exposed as fields
+#[derive(Deserialize)]
+struct Record<'a> {
+    #[serde(borrow)]
+    country: Option<Place<'a>>,
+    #[serde(borrow)]
+    city: Option<Place<'a>>,
+    location: Option<Location>,
+    autonomous_system_number: Option<u32>,
+    autonomous_system_organization: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct Place<'a> {
+    iso_code: Option<&'a str>,
+    #[serde(borrow)]
+    names: Option<BTreeMap<&'a str, &'a str>>,
+}
+
+impl<'a> Place<'a> {
+    fn name(&self) -> Option<&'a str> {
+        self.names.as_ref()?.get("en").copied()
+    }
+}
+
+#[derive(Deserialize)]
+struct Location {
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Geo<'a> {
+    country: Option<&'a str>,
+    country_name: Option<&'a str>,
+    city: Option<&'a str>,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    asn: Option<u32>,
+    as_org: Option<&'a str>,
+}
+
+impl GeoIp {
+    fn lookup(&self, ip: IpAddr) -> Option<Geo<'_>> {
+        let r: Record = self.reader.lookup(ip).ok()?;
+        Some(Geo {
+            country: r.country.as_ref().and_then(|c| c.iso_code),
+            country_name: r.country.as_ref().and_then(Place::name),
+            city: r.city.as_ref().and_then(Place::name),
+            latitude: r.location.as_ref().and_then(|l| l.latitude),
+            longitude: r.location.as_ref().and_then(|l| l.longitude),
+            asn: r.autonomous_system_number,
+            as_org: r.autonomous_system_organization,
+        })
+    }
+}
+
+pub(crate) struct GeoIpInst<'req> {
+    db: &'req GeoIp,
+
+    /// Lookups performed by this query. Log lines from the same client tend to
+    /// be repeated, and decoding a record is much slower than hashing the address.
+    cache: RefCell<HashMap<IpAddr, Option<Geo<'req>>>>,
+}
+
+impl<'req> GeoIpInst<'req> {
+    pub(crate) fn new(db: &'req GeoIp) -> Self {
+        GeoIpInst { db, cache: RefCell::new(HashMap::new()) }
+    }
+
+    fn lookup(&self, ip: IpAddr) -> Option<Geo<'req>> {
+        let mut cache = self.cache.borrow_mut();
+        if let Some(geo) = cache.get(&ip) {
+            return *geo;
+        }
+
+        if cache.len() >= CACHE_SIZE {
+            cache.clear();
+        }
+        *cache.entry(ip).or_insert_with(|| self.db.lookup(ip))
+    }
+}
+
+impl<'req> ParserInst for GeoIpInst<'req> {
+    fn require_field(&mut self, field: &str) -> Option<usize> {
+        FIELDS.iter().position(|&(x, _)| x == field)
+    }
+
+    fn parse<'b>(&self, bump: &'b Bump, input: &mut FieldVal<'b>) -> Option<&'b mut [FieldVal<'b>]> {
+        let ip = match *input {
+            FieldVal::Ip(ip) => ip,
+            FieldVal::String(s) => s.trim().parse().ok()?,
+            _ => return None,
+        };
+        *input = FieldVal::Ip(ip);
+
+        // Addresses missing from the database, such as private networks, have null children
+        let geo = self.lookup(ip).unwrap_or_default();
+
+        let string = |v: Option<&str>| v.map_or(FieldVal::Null, |s| FieldVal::String(bump.alloc_str(s)));
+        let number = |v: Option<f64>| v.map_or(FieldVal::Null, FieldVal::Number);
+
+        Some(bump.alloc([
+            string(geo.country),
+            string(geo.country_name),
+            string(geo.city),
+            number(geo.latitude),
+            number(geo.longitude),
+            number(geo.asn.map(f64::from)),
+            string(geo.as_org),
+        ]))
+    }
+}
+
+/// Encoders for the MaxMind DB data section format, described at <https://maxmind.github.io/MaxMind-DB/>
+#[cfg(test)]
+mod encode {
+    pub fn ctrl(ty: u8, size: usize) -> Vec<u8> {
+        assert!(size < 29);
+        if ty <= 7 { vec![ty << 5 | size as u8] } else { vec![size as u8, ty - 7] }
+    }
+
+    pub fn string(s: &str) -> Vec<u8> {
+        [ctrl(2, s.len()), s.as_bytes().to_vec()].concat()
+    }
+
+    pub fn double(v: f64) -> Vec<u8> {
+        [ctrl(3, 8), v.to_be_bytes().to_vec()].concat()
+    }
+
+    pub fn uint(ty: u8, v: u64) -> Vec<u8> {
+        let bytes = v.to_be_bytes();
+        let start = bytes.iter().position(|&b| b != 0).unwrap_or(8);
+        [ctrl(ty, 8 - start), bytes[start..].to_vec()].concat()
+    }
+
+    pub fn map(entries: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut out = ctrl(7, entries.len());
+        for (k, v) in entries {
+            out.extend(string(k));
+            out.extend_from_slice(v);
+        }
+        out
+    }
+}
+
+/// Build an IPv4 MaxMind DB containing `record` for 0.0.0.0/1.
+#[cfg(test)]
+fn test_database(record: Vec<u8>) -> Vec<u8> {
+    use encode::*;
+
+    let node_count = 1u32;
+    // Left record points to the start of the data section, right record is "not found"
+    let data_pointer = node_count + 16;
+    let mut db = [&data_pointer.to_be_bytes()[1..], &node_count.to_be_bytes()[1..]].concat();
+    db.extend([0; 16]);
+    db.extend(record);
+    db.extend(b"\xab\xcd\xefMaxMind.com");
+    db.extend(map(&[
+        ("binary_format_major_version", uint(5, 2)),
+        ("binary_format_minor_version", uint(5, 0)),
+        ("build_epoch", uint(9, 0)),
+        ("database_type", string("Test")),
+        ("description", map(&[])),
+        ("ip_version", uint(5, 4)),
+        ("languages", ctrl(11, 0)),
+        ("node_count", uint(6, node_count.into())),
+        ("record_size", uint(5, 24)),
+    ]));
+    db
+}
+
+#[test]
+fn test() {
+    use encode::*;
+    let record = map(&[
+        ("country", map(&[("iso_code", string("SE")), ("names", map(&[("de", string("Schweden")), ("en", string("Sweden"))]))])),
+        ("city", map(&[("geoname_id", uint(6, 2694762)), ("names", map(&[("en", string("Linköping"))]))])),
+        ("location", map(&[("latitude", double(58.4167)), ("longitude", double(15.6167))])),
+        ("autonomous_system_number", uint(6, 40218)),
+    ]);
+    let db = GeoIp { reader: Arc::new(Reader::from_source(test_database(record)).unwrap()) };
+    let mut inst = GeoIpInst::new(&db);
+    assert_eq!(inst.require_field("city"), Some(2));
+
+    let bump = Bump::new();
+    let mut input = FieldVal::String("89.160.20.128");
+    assert_eq!(inst.parse(&bump, &mut input).unwrap(), &[
+        FieldVal::String("SE"),
+        FieldVal::String("Sweden"),
+        FieldVal::String("Linköping"),
+        FieldVal::Number(58.4167),
+        FieldVal::Number(15.6167),
+        FieldVal::Number(40218.0),
+        FieldVal::Null,
+    ]);
+    assert_eq!(input, FieldVal::Ip("89.160.20.128".parse().unwrap()));
+
+    // Cached
+    assert_eq!(inst.parse(&bump, &mut input).unwrap()[0], FieldVal::String("SE"));
+    assert_eq!(inst.cache.borrow().len(), 1);
+
+    // Not in the database
+    assert_eq!(inst.parse(&bump, &mut FieldVal::String("192.168.0.1")).unwrap(), &[FieldVal::Null; 7]);
+
+    assert!(inst.parse(&bump, &mut FieldVal::String("not an address")).is_none());
+
+    let missing = toml::from_str::<GeoIp>(r#"database = "/nonexistent/GeoLite2-City.mmdb""#);
+    assert!(missing.is_err_and(|e| e.to_string().contains("/nonexistent/GeoLite2-City.mmdb")));
+
+    let relative = toml::from_str::<GeoIp>(r#"database = "GeoLite2-City.mmdb""#);
+    assert!(relative.is_err_and(|e| e.to_string().contains("must be absolute")));
+}
@@ -0,0 +1,325 @@
+use bumpalo::Bump;
+use indexmap::IndexMap;
+use serde::Deserialize;
+
+use crate::{query::FieldVal, FieldDefaults, api::fields::FieldType};
+
+use super::ParserInst;
+
+/// Built-in named patterns, following the Logstash grok pattern library.
+///
+/// Lookaround and atomic groups are not supported by the `regex` crate, so
+/// patterns that use them in Logstash are approximated.
+const PATTERNS: &[(&str, &str)] = &[
+    ("USERNAME", r"[a-zA-Z0-9._-]+"),
+    ("USER", r"%{USERNAME}"),
+    ("EMAILLOCALPART", r"[a-zA-Z0-9!#$%&'*+/=?^_`{|}~-]+(?:\.[a-zA-Z0-9!#$%&'*+/=?^_`{|}~-]+)*"),
+    ("EMAILADDRESS", r"%{EMAILLOCALPART}@%{HOSTNAME}"),
+    ("INT", r"[+-]?[0-9]+"),
+    ("BASE10NUM", r"[+-]?(?:[0-9]+(?:\.[0-9]+)?|\.[0-9]+)"),
+    ("NUMBER", r"%{BASE10NUM}"),
+    ("BASE16NUM", r"[+-]?(?:0x)?[0-9A-Fa-f]+"),
+    ("POSINT", r"\b[1-9][0-9]*\b"),
+    ("NONNEGINT", r"\b[0-9]+\b"),
+    ("WORD", r"\b\w+\b"),
+    ("NOTSPACE", r"\S+"),
+    ("SPACE", r"\s*"),
+    ("DATA", r".*?"),
+    ("GREEDYDATA", r".*"),
+    ("QUOTEDSTRING", r#""(?:[^"\\]|\\.)*"|'(?:[^'\\]|\\.)*'|`(?:[^`\\]|\\.)*`"#),
+    ("QS", r"%{QUOTEDSTRING}"),
+    ("UUID", r"[A-Fa-f0-9]{8}-(?:[A-Fa-f0-9]{4}-){3}[A-Fa-f0-9]{12}"),
+
+    // Networking
+    ("CISCOMAC", r"(?:[A-Fa-f0-9]{4}\.){2}[A-Fa-f0-9]{4}"),
+    ("WINDOWSMAC", r"(?:[A-Fa-f0-9]{2}-){5}[A-Fa-f0-9]{2}"),
+    ("COMMONMAC", r"(?:[A-Fa-f0-9]{2}:){5}[A-Fa-f0-9]{2}"),
+    ("MAC", r"%{CISCOMAC}|%{WINDOWSMAC}|%{COMMONMAC}"),
+    ("IPV4", r"(?:(?:25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)\.){3}(?:25[0-5]|2[0-4][0-9]|[01]?[0-9][0-9]?)"),
+    ("IPV6", concat!(
+        r"(?:[0-9A-Fa-f]{1,4}:){7}[0-9A-Fa-f]{1,4}",
+        r"|(?:[0-9A-Fa-f]{1,4}:){6}%{IPV4}",
+        r"|::(?:[Ff]{4}(?::0{1,4})?:)?%{IPV4}",
+        r"|(?:[0-9A-Fa-f]{1,4}:){1,6}:[0-9A-Fa-f]{1,4}",
+        r"|(?:[0-9A-Fa-f]{1,4}:){1,5}(?::[0-9A-Fa-f]{1,4}){1,2}",
+        r"|(?:[0-9A-Fa-f]{1,4}:){1,4}(?::[0-9A-Fa-f]{1,4}){1,3}",
+        r"|(?:[0-9A-Fa-f]{1,4}:){1,3}(?::[0-9A-Fa-f]{1,4}){1,4}",
+        r"|(?:[0-9A-Fa-f]{1,4}:){1,2}(?::[0-9A-Fa-f]{1,4}){1,5}",
+        r"|[0-9A-Fa-f]{1,4}:(?::[0-9A-Fa-f]{1,4}){1,6}",
+        r"|:(?::[0-9A-Fa-f]{1,4}){1,7}",
+        r"|(?:[0-9A-Fa-f]{1,4}:){1,7}:",
+        r"|::",
+    )),
+    ("IP", r"%{IPV6}|%{IPV4}"),
+    ("HOSTNAME", r"\b[0-9A-Za-z][0-9A-Za-z-]{0,62}(?:\.[0-9A-Za-z][0-9A-Za-z-]{0,62})*\.?"),
+    ("IPORHOST", r"%{IP}|%{HOSTNAME}"),
+    ("HOSTPORT", r"%{IPORHOST}:%{POSINT}"),
+
+    // Paths and URIs
+    ("PATH", r"%{UNIXPATH}|%{WINPATH}"),
+    ("UNIXPATH", r"(?:/[\w_%!$@:.,+~-]*)+"),
+    ("TTY", r"/dev/(?:pts|tty(?:[pq])?)(?:\w+)?/?(?:[0-9]+)"),
+    ("WINPATH", r"(?:[A-Za-z]+:|\\)(?:\\[^\\?*]*)+"),
+    ("URIPROTO", r"[A-Za-z][A-Za-z0-9+\-.]+"),
+    ("URIHOST", r"%{IPORHOST}(?::%{POSINT})?"),
+    ("URIPATH", r"(?:/[A-Za-z0-9$.+!*'(){},~:;=@#%&_\-]*)+"),
+    ("URIPARAM", r"\?[A-Za-z0-9$.+!*'|(){},~@#%&/=:;_?\-\[\]<>]*"),
+    ("URIPATHPARAM", r"%{URIPATH}(?:%{URIPARAM})?"),
+    ("URI", r"%{URIPROTO}://(?:%{USER}(?::[^@]*)?@)?(?:%{URIHOST})?(?:%{URIPATHPARAM})?"),
+
+    // Dates and times
+    ("MONTH", r"\b(?:[Jj]an(?:uary|uar)?|[Ff]eb(?:ruary|ruar)?|[Mm](?:a|ä)?r(?:ch|z)?|[Aa]pr(?:il)?|[Mm]a(?:y|i)?|[Jj]un(?:e|i)?|[Jj]ul(?:y|i)?|[Aa]ug(?:ust)?|[Ss]ep(?:tember)?|[Oo](?:c|k)?t(?:ober)?|[Nn]ov(?:ember)?|[Dd]e(?:c|z)(?:ember)?)\b"),
+    ("MONTHNUM", r"0?[1-9]|1[0-2]"),
+    ("MONTHNUM2", r"0[1-9]|1[0-2]"),
+    ("MONTHDAY", r"0[1-9]|[12][0-9]|3[01]|[1-9]"),
+    ("DAY", r"Mon(?:day)?|Tue(?:sday)?|Wed(?:nesday)?|Thu(?:rsday)?|Fri(?:day)?|Sat(?:urday)?|Sun(?:day)?"),
+    ("YEAR", r"(?:\d\d){1,2}"),
+    ("HOUR", r"2[0123]|[01]?[0-9]"),
+    ("MINUTE", r"[0-5][0-9]"),
+    ("SECOND", r"(?:[0-5]?[0-9]|60)(?:[:.,][0-9]+)?"),
+    ("TIME", r"%{HOUR}:%{MINUTE}(?::%{SECOND})?"),
+    ("DATE_US", r"%{MONTHNUM}[/-]%{MONTHDAY}[/-]%{YEAR}"),
+    ("DATE_EU", r"%{MONTHDAY}[./-]%{MONTHNUM}[./-]%{YEAR}"),
+    ("ISO8601_TIMEZONE", r"Z|[+-]%{HOUR}(?::?%{MINUTE})"),
+    ("ISO8601_SECOND", r"%{SECOND}"),
+    ("TIMESTAMP_ISO8601", r"%{YEAR}-%{MONTHNUM}-%{MONTHDAY}[T ]%{HOUR}:?%{MINUTE}(?::?%{SECOND})?%{ISO8601_TIMEZONE}?"),
+    ("DATE", r"%{DATE_US}|%{DATE_EU}"),
+    ("DATESTAMP", r"%{DATE}[- ]%{TIME}"),
+    ("TZ", r"[APMCE][SD]T|UTC"),
+    ("DATESTAMP_RFC822", r"%{DAY} %{MONTH} %{MONTHDAY} %{YEAR} %{TIME} %{TZ}"),
+    ("DATESTAMP_RFC2822", r"%{DAY}, %{MONTHDAY} %{MONTH} %{YEAR} %{TIME} %{ISO8601_TIMEZONE}"),
+    ("DATESTAMP_OTHER", r"%{DAY} %{MONTH} %{MONTHDAY} %{TIME} %{TZ} %{YEAR}"),
+    ("DATESTAMP_EVENTLOG", r"%{YEAR}%{MONTHNUM2}%{MONTHDAY}%{HOUR}%{MINUTE}%{SECOND}"),
+    ("HTTPDERROR_DATE", r"%{DAY} %{MONTH} %{MONTHDAY} %{TIME} %{YEAR}"),
+    ("SYSLOGTIMESTAMP", r"%{MONTH} +%{MONTHDAY} %{TIME}"),
+    ("HTTPDATE", r"%{MONTHDAY}/%{MONTH}/%{YEAR}:%{TIME} %{INT}"),
+
+    // Syslog
+    ("PROG", r"[\x21-\x5a\x5c\x5e-\x7e]+"),
+    ("SYSLOGPROG", r"%{PROG:program}(?:\[%{POSINT:pid}\])?"),
+    ("SYSLOGHOST", r"%{IPORHOST}"),
+    ("SYSLOGFACILITY", r"<%{NONNEGINT:facility}.%{NONNEGINT:priority}>"),
+    ("SYSLOGBASE", r"%{SYSLOGTIMESTAMP:timestamp} (?:%{SYSLOGFACILITY} )?%{SYSLOGHOST:logsource} %{SYSLOGPROG}:"),
+
+    // Web servers
+    ("HTTPDUSER", r"%{EMAILADDRESS}|%{USER}"),
+    ("COMMONAPACHELOG", r#"%{IPORHOST:clientip} %{HTTPDUSER:ident} %{USER:auth} \[%{HTTPDATE:timestamp}\] "(?:%{WORD:verb} %{NOTSPACE:request}(?: HTTP/%{NUMBER:httpversion})?|%{DATA:rawrequest})" %{NUMBER:response} (?:%{NUMBER:bytes}|-)"#),
+    ("COMBINEDAPACHELOG", r"%{COMMONAPACHELOG} %{QS:referrer} %{QS:agent}"),
+
+    // Log levels
+    ("LOGLEVEL", r"[Aa]lert|ALERT|[Tt]race|TRACE|[Dd]ebug|DEBUG|[Nn]otice|NOTICE|[Ii]nfo?(?:rmation)?|INFO?(?:RMATION)?|[Ww]arn?(?:ing)?|WARN?(?:ING)?|[Ee]rr?(?:or)?|ERR?(?:OR)?|[Cc]rit?(?:ical)?|CRIT?(?:ICAL)?|[Ff]atal|FATAL|[Ss]evere|SEVERE|EMERG(?:ENCY)?|[Ee]merg(?:ency)?"),
+];
+
+/// Maximum nesting of pattern references, to reject recursive definitions
+const MAX_DEPTH: usize = 32;
+
+#[derive(Clone, Deserialize)]
+#[serde(try_from = "GrokConfig")]
+pub struct Grok {
+    regex: regex::Regex,
+
+    /// Names and types of the extracted fields
+    fields: Vec<(String, FieldType)>,
+
+    /// Capture group index and the field it populates
+    groups: Vec<(usize, usize)>,
+}
+
+#[derive(Deserialize)]
+struct GrokConfig {
+    pattern: String,
+
+    /// Additional named patterns, which take precedence over the built-in patterns
+    #[serde(default)]
+    patterns: IndexMap<String, String>,
+}
+
+impl TryFrom<GrokConfig> for Grok {
+    type Error = String;
+
+    fn try_from(config: GrokConfig) -> Result<Self, Self::Error> {
+        Grok::new(&config.pattern, &config.patterns)
+    }
+}
+
+struct Expander<'a> {
+    patterns: &'a IndexMap<String, String>,
+    regex: String,
+
+    /// Field name and type suffix for each generated capture group
+    captures: Vec<(String, Option<FieldType>)>,
+}
+
+impl<'a> Expander<'a> {
+    fn lookup(&self, name: &str) -> Option<&'a str> {
+        self.patterns.get(name).map(|p| &p[..])
+            .or_else(|| PATTERNS.iter().find(|(n, _)| *n == name).map(|(_, p)| *p))
+    }
+
+    fn expand(&mut self, mut pattern: &str, depth: usize) -> Result<(), String> {
+        if depth > MAX_DEPTH {
+            return Err("grok patterns nested too deeply".into());
+        }
+
+        while let Some((prefix, rest)) = pattern.split_once("%{") {
+            self.push_literal(prefix);
+
+            let (reference, rest) = rest.split_once('}').ok_or("unterminated reference in grok pattern")?;
+            let mut parts = reference.splitn(3, ':');
+            let name = parts.next().unwrap_or_default();
+            let field = parts.next();
+            let ty = parts.next().map(|ty| match ty {
+                "int" | "float" | "number" => Ok(FieldType::Number),
+                "string" | "keyword" => Ok(FieldType::Keyword),
+                _ => Err(format!("unknown type `{ty}` in grok pattern")),
+            }).transpose()?;
+
+            let definition = self.lookup(name).ok_or_else(|| format!("unknown grok pattern `{name}`"))?;
+
+            if let Some(field) = field {
+                self.regex.push_str(&format!("(?P<_grok{}>", self.captures.len()));
+                self.captures.push((field.to_owned(), ty));
+            } else {
+                self.regex.push_str("(?:");
+            }
+            self.expand(definition, depth + 1)?;
+            self.regex.push(')');
+
+            pattern = rest;
+        }
+
+        self.push_literal(pattern);
+        Ok(())
+    }
+
+    /// Append regex syntax outside of a `%{}` reference, converting Oniguruma-style `(?<name>` groups.
+    fn push_literal(&mut self, mut s: &str) {
+        while let Some(i) = s.find("(?<") {
+            let (before, after) = s.split_at(i + 3);
+            self.regex.push_str(&before[..i]);
+            self.regex.push_str(if after.starts_with(['=', '!']) { "(?<" } else { "(?P<" });
+            s = after;
+        }
+        self.regex.push_str(s);
+    }
+}
+
+impl Grok {
+    pub fn new(pattern: &str, patterns: &IndexMap<String, String>) -> Result<Grok, String> {
+        let mut expander = Expander { patterns, regex: String::new(), captures: Vec::new() };
+        expander.expand(pattern, 0)?;
+
+        let regex = regex::Regex::new(&expander.regex).map_err(|e| e.to_string())?;
+
+        let mut fields: Vec<(String, FieldType)> = Vec::new();
+        let mut groups = Vec::new();
+        for (group, name) in regex.capture_names().enumerate() {
+            let Some(name) = name else { continue };
+
+            let (field, ty) = match name.strip_prefix("_grok").and_then(|i| i.parse::<usize>().ok()) {
+                Some(i) => expander.captures[i].clone(),
+                None => (name.to_owned(), None),
+            };
+
+            // A field name may be captured more than once, e.g. in alternatives
+            let field_index = if let Some(i) = fields.iter().position(|(f, _)| *f == field) {
+                if let Some(ty) = ty { fields[i].1 = ty; }
+                i
+            } else {
+                fields.push((field, ty.unwrap_or(FieldType::Keyword)));
+                fields.len() - 1
+            };
+
+            groups.push((group, field_index));
+        }
+
+        Ok(Grok { regex, fields, groups })
+    }
+
+    fn parse_with<'a>(&self, s: &'a str, mut set: impl FnMut(usize, &'a str)) -> bool {
+        let mut locs = self.regex.capture_locations();
+        if self.regex.captures_read(&mut locs, s).is_none() {
+            return false;
+        }
+
+        for &(group, field) in &self.groups {
+            if let Some((start, end)) = locs.get(group) {
+                set(field, &s[start..end]);
+            }
+        }
+
+        true
+    }
+
+    #[cfg(test)]
+    fn parse<'a>(&self, s: &'a str) -> Option<Vec<(&str, &'a str)>> {
+        let mut results = Vec::new();
+        if self.parse_with(s, |field, v| results.push((&self.fields[field].0[..], v))) {
+            Some(results)
+        } else { None }
+    }
+}
+
+pub(crate) fn fields(g: &Grok) -> Vec<(&str, FieldDefaults)> {
+    g.fields.iter().map(|(name, ty)| (&name[..], FieldDefaults { ty: *ty })).collect()
+}
+
+pub(crate) struct GrokInst<'req>(pub(crate) &'req Grok);
+
+impl<'req> ParserInst for GrokInst<'req> {
+    fn require_field(&mut self, field: &str) -> Option<usize> {
+        self.0.fields.iter().position(|(x, _)| x == field)
+    }
+
+    fn parse<'b>(&self, bump: &'b Bump, input: &mut FieldVal<'b>) -> Option<&'b mut [FieldVal<'b>]> {
+        let input = input.as_str().unwrap_or_default();
+        let results = bump.alloc_slice_fill_copy(self.0.fields.len(), FieldVal::Null);
+        let matched = self.0.parse_with(input, |field, v| {
+            if results[field].exists() { return }
+            results[field] = match self.0.fields[field].1 {
+                FieldType::Number => v.parse().map_or_else(|_| FieldVal::String(bump.alloc_str(v)), FieldVal::Number),
+                _ => FieldVal::String(bump.alloc_str(v)),
+            };
+        });
+        matched.then_some(results)
+    }
+}
+
+#[test]
+fn test() {
+    let no_patterns = IndexMap::new();
+
+    let g = Grok::new(r"%{IP:client} %{WORD:method} %{URIPATHPARAM:request} %{NUMBER:bytes:int} %{NUMBER:duration}", &no_patterns).unwrap();
+    assert_eq!(g.fields, vec![
+        ("client".to_owned(), FieldType::Keyword),
+        ("method".to_owned(), FieldType::Keyword),
+        ("request".to_owned(), FieldType::Keyword),
+        ("bytes".to_owned(), FieldType::Number),
+        ("duration".to_owned(), FieldType::Keyword),
+    ]);
+    assert_eq!(g.parse("55.3.244.1 GET /index.html 15824 0.043"),
+        Some(vec![("client", "55.3.244.1"), ("method", "GET"), ("request", "/index.html"), ("bytes", "15824"), ("duration", "0.043")]));
+    assert_eq!(g.parse("2001:db8::ff00:42:8329 GET /index.html?a=b 0 1").unwrap()[0], ("client", "2001:db8::ff00:42:8329"));
+    assert_eq!(g.parse("not a match"), None);
+
+    let combined = Grok::new("%{COMBINEDAPACHELOG}", &no_patterns).unwrap();
+    assert_eq!(combined.parse(r#"1.2.3.4 - - [30/Apr/1998:22:00:52 +0000] "GET /some/path?a=b HTTP/1.0" 200 3171 "-" "Mozilla/4.0""#),
+        Some(vec![
+            ("clientip", "1.2.3.4"), ("ident", "-"), ("auth", "-"), ("timestamp", "30/Apr/1998:22:00:52 +0000"),
+            ("verb", "GET"), ("request", "/some/path?a=b"), ("httpversion", "1.0"), ("response", "200"), ("bytes", "3171"),
+            ("referrer", r#""-""#), ("agent", r#""Mozilla/4.0""#),
+        ]));
+
+    let syslog = Grok::new("%{SYSLOGBASE} %{GREEDYDATA:message}", &no_patterns).unwrap();
+    assert_eq!(syslog.parse("Mar  1 12:00:01 myhost sshd[1234]: Accepted publickey"),
+        Some(vec![("timestamp", "Mar  1 12:00:01"), ("logsource", "myhost"), ("program", "sshd"), ("pid", "1234"), ("message", "Accepted publickey")]));
+
+    let custom = [("REQID".to_owned(), "req-[0-9a-f]+".to_owned())].into_iter().collect();
+    let g = Grok::new(r"%{LOGLEVEL:level} %{REQID:id} (?<rest>.*)", &custom).unwrap();
+    assert_eq!(g.parse("WARN req-1f2e slow"), Some(vec![("level", "WARN"), ("id", "req-1f2e"), ("rest", "slow")]));
+
+    assert!(Grok::new("%{NOPE:x}", &no_patterns).is_err());
+    assert!(Grok::new("%{INT:x:bool}", &no_patterns).is_err());
+    let recursive = [("A".to_owned(), "%{A}".to_owned())].into_iter().collect();
+    assert!(Grok::new("%{A}", &recursive).is_err());
+}
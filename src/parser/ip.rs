@@ -0,0 +1,78 @@
+use std::net::{IpAddr, SocketAddr};
+
+use bumpalo::Bump;
+
+use crate::{query::FieldVal, FieldDefaults, api::fields::FieldType};
+
+use super::ParserInst;
+
+const FIELDS: &[(&str, FieldType)] = &[
+    ("version", FieldType::Number),
+    ("private", FieldType::Boolean),
+    ("loopback", FieldType::Boolean),
+];
+
+pub(crate) fn fields() -> Vec<(&'static str, FieldDefaults)> {
+    super::typed_fields(FIELDS)
+}
+
+/// Parse an address, ignoring a port as in `10.0.0.1:443` or `[::1]:443`.
+fn parse(s: &str) -> Option<IpAddr> {
+    let s = s.trim();
+    s.parse().ok().or_else(|| s.parse::<SocketAddr>().ok().map(|a| a.ip()))
+}
+
+/// Private-use networks: RFC 1918 for IPv4 and unique local addresses (fc00::/7) for IPv6
+fn is_private(ip: IpAddr) -> bool {
+    match ip.to_canonical() {
+        IpAddr::V4(ip) => ip.is_private(),
+        IpAddr::V6(ip) => ip.segments()[0] & 0xfe00 == 0xfc00,
+    }
+}
+
+pub(crate) struct Ip;
+
+impl ParserInst for Ip {
+    fn require_field(&mut self, field: &str) -> Option<usize> {
+        FIELDS.iter().position(|&(x, _)| x == field)
+    }
+
+    fn parse<'b>(&self, bump: &'b Bump, input: &mut FieldVal<'b>) -> Option<&'b mut [FieldVal<'b>]> {
+        let ip = match *input {
+            FieldVal::Ip(ip) => ip,
+            FieldVal::String(s) => parse(s)?,
+            _ => return None,
+        };
+        *input = FieldVal::Ip(ip);
+
+        Some(bump.alloc([
+            FieldVal::Number(if ip.to_canonical().is_ipv4() { 4.0 } else { 6.0 }),
+            FieldVal::Bool(is_private(ip)),
+            FieldVal::Bool(ip.to_canonical().is_loopback()),
+        ]))
+    }
+}
+
+#[test]
+fn test() {
+    assert_eq!(parse(" 192.168.0.1 "), Some("192.168.0.1".parse().unwrap()));
+    assert_eq!(parse("10.0.0.1:443"), Some("10.0.0.1".parse().unwrap()));
+    assert_eq!(parse("[::1]:8080"), Some("::1".parse().unwrap()));
+    assert_eq!(parse("2001:db8::1"), Some("2001:db8::1".parse().unwrap()));
+    assert_eq!(parse("example.com"), None);
+    assert_eq!(parse("-"), None);
+
+    let bump = Bump::new();
+    let flags = |s: &'static str| {
+        let mut input = FieldVal::String(s);
+        let vals = Ip.parse(&bump, &mut input).unwrap();
+        assert!(matches!(input, FieldVal::Ip(_)));
+        (vals[0], vals[1] == FieldVal::Bool(true), vals[2] == FieldVal::Bool(true))
+    };
+    assert_eq!(flags("172.16.5.4"), (FieldVal::Number(4.0), true, false));
+    assert_eq!(flags("8.8.8.8"), (FieldVal::Number(4.0), false, false));
+    assert_eq!(flags("127.0.0.1"), (FieldVal::Number(4.0), false, true));
+    assert_eq!(flags("::ffff:127.0.0.1"), (FieldVal::Number(4.0), false, true));
+    assert_eq!(flags("fd12::1"), (FieldVal::Number(6.0), true, false));
+    assert_eq!(flags("::1"), (FieldVal::Number(6.0), false, true));
+}
@@ -59,7 +59,7 @@ impl <'de, 'b: 'de> DeserializeSeed<'de> for Seed<'b> {
             }
 
             fn visit_bool<E>(self, v: bool) -> Result<FieldVal<'b>, E> {
-                Ok(if v { FieldVal::String("true") } else { FieldVal::String("false") })
+                Ok(FieldVal::Bool(v))
             }
 
             fn visit_i64<E>(self, v: i64) -> Result<FieldVal<'b>, E> {
@@ -87,8 +87,13 @@ impl <'de, 'b: 'de> DeserializeSeed<'de> for Seed<'b> {
             }
 
             fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
-                while let Some(serde::de::IgnoredAny) = seq.next_element()? {}
-                Ok(FieldVal::Null)
+                let mut items = BVec::with_capacity_in(seq.size_hint().unwrap_or(8), self.0);
+
+                while let Some(value) = seq.next_element_seed(Seed(self.0))? {
+                    items.push(value);
+                }
+
+                Ok(FieldVal::Array(items.into_bump_slice()))
             }
 
             fn visit_map<A: serde::de::MapAccess<'de>>(self, mut access: A) -> Result<FieldVal<'b>, A::Error> {
@@ -111,32 +116,37 @@ impl ParserInst for Json {
         Some(self.fields.insert_full(field.to_owned()).0)
     }
 
-    fn parse<'b>(&self, bump: &'b bumpalo::Bump, input: &mut FieldVal<'b>) -> &'b mut [FieldVal<'b>] {
+    fn parse<'b>(&self, bump: &'b bumpalo::Bump, input: &mut FieldVal<'b>) -> Option<&'b mut [FieldVal<'b>]> {
         let v = match input {
-            FieldVal::String(s) => parse(bump, s),
+            FieldVal::String(s) => parse(bump, s)?,
             v => *v,
         };
 
-        self.fields.iter().map(|key| {
+        Some(self.fields.iter().map(|key| {
             deref(v, key)
-        }).collect_in::<BVec<_>>(bump).into_bump_slice_mut()
+        }).collect_in::<BVec<_>>(bump).into_bump_slice_mut())
     }
 }
 
-fn parse<'b>(bump: &'b Bump, s: &str) -> FieldVal<'b> {
-     Seed(bump).deserialize(&mut serde_json::Deserializer::from_str(s)).unwrap_or(FieldVal::Null)
+fn parse<'b>(bump: &'b Bump, s: &str) -> Option<FieldVal<'b>> {
+     Seed(bump).deserialize(&mut serde_json::Deserializer::from_str(s)).ok()
 }
 
+/// Look up a dotted path, where array elements are accessed by index and
+/// `length` is the number of elements of an array.
 fn deref<'b>(mut v: FieldVal<'b>, path: &str) -> FieldVal<'b> {
     for path_part in path.split(".") {
-        if let FieldVal::Map(pairs) = v {
-            if let Some((_, child)) = pairs.iter().find(|(k, _)| *k == path_part) {
-                v = *child;
-            } else {
-                return FieldVal::Null;
+        v = match v {
+            FieldVal::Map(pairs) => match pairs.iter().find(|(k, _)| *k == path_part) {
+                Some((_, child)) => *child,
+                None => return FieldVal::Null,
+            }
+            FieldVal::Array(items) if path_part == "length" => FieldVal::Number(items.len() as f64),
+            FieldVal::Array(items) => match path_part.parse().ok().and_then(|i: usize| items.get(i)) {
+                Some(child) => *child,
+                None => return FieldVal::Null,
             }
-        } else {
-            return FieldVal::Null
+            _ => return FieldVal::Null,
         }
     }
     v
@@ -145,13 +155,22 @@ fn deref<'b>(mut v: FieldVal<'b>, path: &str) -> FieldVal<'b> {
 #[test]
 fn test_parse_line() {
     let bump = Bump::new();
-    let v = parse(&bump, r#"{"ignored": {}, "foo": 5, "bar": {"baz": "test"}, "dotted.name": 6, "obj": {}, "arr": [5], "bool": false, "n": null}"#);
+    let v = parse(&bump, r#"{"ignored": {}, "foo": 5, "bar": {"baz": "test"}, "dotted.name": 6, "obj": {}, "arr": [5], "tags": ["a", {"b": [true]}], "bool": false, "n": null}"#).unwrap();
 
     assert_eq!(deref(v, "foo"), FieldVal::Number(5.0));
     assert_eq!(deref(v, "bar.baz"), FieldVal::String("test"));
+    assert_eq!(deref(v, "bar").to_string(), r#"{"baz":"test"}"#);
+    assert_eq!(deref(parse(&bump, r#"{"a": {"x": 1, "y": 2.5}}"#).unwrap(), "a").to_string(), r#"{"x":1,"y":2.5}"#);
+    assert_eq!(deref(v, "obj").to_string(), "{}");
     assert_eq!(deref(v, "x"), FieldVal::Null);
     assert_eq!(deref(v, "n"), FieldVal::Null);
-    assert_eq!(deref(v, "arr"), FieldVal::Null);
-    assert_eq!(deref(v, "bool"), FieldVal::String("false"));
+    assert_eq!(deref(v, "arr"), FieldVal::Array(&[FieldVal::Number(5.0)]));
+    assert_eq!(deref(v, "arr.0"), FieldVal::Number(5.0));
+    assert_eq!(deref(v, "arr.1"), FieldVal::Null);
+    assert_eq!(deref(v, "arr.x"), FieldVal::Null);
+    assert_eq!(deref(v, "tags.length"), FieldVal::Number(2.0));
+    assert_eq!(deref(v, "tags.1.b.0"), FieldVal::Bool(true));
+    assert_eq!(deref(v, "tags").to_string(), r#"["a",{"b":[true]}]"#);
+    assert_eq!(deref(v, "bool"), FieldVal::Bool(false));
 }
 
@@ -0,0 +1,209 @@
+use std::sync::OnceLock;
+
+use bumpalo::Bump;
+use bumpalo::collections::String as BString;
+use indexmap::IndexSet;
+use serde::Deserialize;
+
+use crate::query::FieldVal;
+
+use super::ParserInst;
+
+#[derive(Clone, Deserialize)]
+#[serde(try_from = "KeyValueConfig")]
+pub struct KeyValue {
+    /// Separator between pairs, or any run of whitespace if unset
+    pair_separator: Option<String>,
+
+    /// Separator between a key and its value
+    value_separator: String,
+
+    /// Character that may surround a value containing separators. Within a
+    /// quoted value, a backslash escapes the following character.
+    quote: Option<char>,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct KeyValueConfig {
+    pair_separator: Option<String>,
+    value_separator: String,
+    quote: Option<char>,
+}
+
+impl Default for KeyValueConfig {
+    fn default() -> Self {
+        // logfmt: `level=info msg="request done" dur=12ms`
+        KeyValueConfig { pair_separator: None, value_separator: "=".into(), quote: Some('"') }
+    }
+}
+
+impl TryFrom<KeyValueConfig> for KeyValue {
+    type Error = &'static str;
+
+    fn try_from(config: KeyValueConfig) -> Result<Self, Self::Error> {
+        // An empty separator would match without consuming any input
+        if config.pair_separator.as_deref() == Some("") {
+            return Err("`pair_separator` must not be empty");
+        }
+        if config.value_separator.is_empty() {
+            return Err("`value_separator` must not be empty");
+        }
+        Ok(KeyValue { pair_separator: config.pair_separator, value_separator: config.value_separator, quote: config.quote })
+    }
+}
+
+/// The configuration of the `logfmt` parser
+pub(crate) fn logfmt() -> &'static KeyValue {
+    static LOGFMT: OnceLock<KeyValue> = OnceLock::new();
+    LOGFMT.get_or_init(|| KeyValueConfig::default().try_into().unwrap())
+}
+
+impl KeyValue {
+    /// Length of the pair separator at the start of `s`, if present
+    fn pair_separator_len(&self, s: &str) -> Option<usize> {
+        match &self.pair_separator {
+            Some(sep) => s.starts_with(&sep[..]).then_some(sep.len()),
+            None => s.chars().next().filter(|c| c.is_whitespace()).map(char::len_utf8),
+        }
+    }
+
+    /// Position of the next pair separator in `s`, or its length
+    fn find_pair_separator(&self, s: &str) -> usize {
+        match &self.pair_separator {
+            Some(sep) => s.find(&sep[..]),
+            None => s.find(char::is_whitespace),
+        }.unwrap_or(s.len())
+    }
+
+    /// Split `s` into key-value pairs, returning `false` if it contains no pairs.
+    fn parse_with<'b>(&self, bump: &'b Bump, mut s: &'b str, mut push: impl FnMut(&'b str, &'b str)) -> bool {
+        let mut any = false;
+
+        loop {
+            while let Some(len) = self.pair_separator_len(s) {
+                s = &s[len..];
+            }
+            if s.is_empty() { break }
+
+            let pair_end = self.find_pair_separator(s);
+            let Some(key_end) = s[..pair_end].find(&self.value_separator[..]) else {
+                // Bare word without a value
+                s = &s[pair_end..];
+                continue;
+            };
+
+            let key = &s[..key_end];
+            s = &s[key_end + self.value_separator.len()..];
+
+            let value = match self.quote {
+                Some(quote) if s.starts_with(quote) => {
+                    let (value, rest) = unquote(bump, &s[quote.len_utf8()..], quote);
+                    s = rest;
+                    value
+                }
+                _ => {
+                    let end = self.find_pair_separator(s);
+                    let value = &s[..end];
+                    s = &s[end..];
+                    value
+                }
+            };
+
+            push(key, value);
+            any = true;
+        }
+
+        any
+    }
+}
+
+/// Read a quoted string up to the closing quote, returning the unescaped value and the remaining input.
+fn unquote<'b>(bump: &'b Bump, s: &'b str, quote: char) -> (&'b str, &'b str) {
+    let Some(end) = s.find([quote, '\\']) else { return (s, "") };
+
+    if s[end..].starts_with(quote) {
+        return (&s[..end], &s[end + quote.len_utf8()..]);
+    }
+
+    let mut value = BString::from_str_in(&s[..end], bump);
+    let mut chars = s[end..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c == quote {
+            return (value.into_bump_str(), &s[end + i + c.len_utf8()..]);
+        } else if c == '\\' {
+            if let Some((_, escaped)) = chars.next() {
+                value.push(escaped);
+            }
+        } else {
+            value.push(c);
+        }
+    }
+    (value.into_bump_str(), "")
+}
+
+pub(crate) struct KeyValueInst<'req> {
+    config: &'req KeyValue,
+    fields: IndexSet<String>,
+}
+
+impl<'req> KeyValueInst<'req> {
+    pub(crate) fn new(config: &'req KeyValue) -> Self {
+        Self { config, fields: IndexSet::new() }
+    }
+}
+
+impl<'req> ParserInst for KeyValueInst<'req> {
+    fn require_field(&mut self, field: &str) -> Option<usize> {
+        Some(self.fields.insert_full(field.to_owned()).0)
+    }
+
+    fn parse<'b>(&self, bump: &'b Bump, input: &mut FieldVal<'b>) -> Option<&'b mut [FieldVal<'b>]> {
+        let FieldVal::String(s) = *input else { return None };
+        let results = bump.alloc_slice_fill_copy(self.fields.len(), FieldVal::Null);
+
+        let any = self.config.parse_with(bump, s, |key, value| {
+            if let Some(i) = self.fields.get_index_of(key) {
+                if !results[i].exists() {
+                    results[i] = FieldVal::String(value);
+                }
+            }
+        });
+
+        any.then_some(results)
+    }
+}
+
+#[cfg(test)]
+fn parse<'b>(config: &KeyValue, bump: &'b Bump, s: &'b str) -> Option<Vec<(&'b str, &'b str)>> {
+    let mut pairs = Vec::new();
+    config.parse_with(bump, s, |k, v| pairs.push((k, v))).then_some(pairs)
+}
+
+#[test]
+fn test() {
+    let bump = Bump::new();
+    let logfmt = logfmt();
+
+    assert_eq!(parse(logfmt, &bump, r#"level=info msg="request done" dur=12ms"#),
+        Some(vec![("level", "info"), ("msg", "request done"), ("dur", "12ms")]));
+    assert_eq!(parse(logfmt, &bump, r#"  a=1   flag b= c="x \"y\" \\z" d="unterminated"#),
+        Some(vec![("a", "1"), ("b", ""), ("c", r#"x "y" \z"#), ("d", "unterminated")]));
+    assert_eq!(parse(logfmt, &bump, "just some words"), None);
+    assert_eq!(parse(logfmt, &bump, "url=http://x/?a=b"), Some(vec![("url", "http://x/?a=b")]));
+
+    let custom = KeyValue { pair_separator: Some(", ".into()), value_separator: ": ".into(), quote: Some('\'') };
+    assert_eq!(parse(&custom, &bump, "user: bob, note: 'a, b', id: 7"),
+        Some(vec![("user", "bob"), ("note", "a, b"), ("id", "7")]));
+
+    assert!(toml::from_str::<KeyValue>(r#"pair_separator = """#).is_err());
+    assert!(toml::from_str::<KeyValue>(r#"value_separator = """#).is_err());
+    assert!(toml::from_str::<KeyValue>(r#"separator = ",""#).is_err());
+    assert!(toml::from_str::<KeyValue>(r#"pair_separator = ",""#).is_ok());
+
+    let mut inst = KeyValueInst::new(logfmt);
+    assert_eq!(inst.require_field("dur"), Some(0));
+    assert_eq!(inst.require_field("missing"), Some(1));
+    let mut input = FieldVal::String("level=info dur=12ms dur=13ms");
+    assert_eq!(inst.parse(&bump, &mut input).unwrap(), &[FieldVal::String("12ms"), FieldVal::Null]);
+}
@@ -3,6 +3,16 @@ use bumpalo::Bump;
 use crate::{query::FieldVal, FieldDefaults, api::fields::FieldType};
 
 pub mod dissect;
+pub mod regex;
+pub mod grok;
+pub mod kv;
+pub mod csv;
+pub mod syslog;
+mod url;
+mod ip;
+pub mod geoip;
+pub mod units;
+pub(crate) mod preset;
 pub mod user_agent;
 pub mod timestamp;
 mod casts;
@@ -11,7 +21,14 @@ mod json;
 pub(crate) trait ParserInst: Send {
     fn require_field(&mut self, field: &str) -> Option<usize>;
 
-    fn parse<'b>(&self, bump: &'b Bump, input: &mut FieldVal<'b>) -> &'b mut [FieldVal<'b>];
+    /// Parse `input`, possibly replacing it with a converted value, and return the
+    /// values of the child fields. Returns `None` if the input could not be parsed.
+    fn parse<'b>(&self, bump: &'b Bump, input: &mut FieldVal<'b>) -> Option<&'b mut [FieldVal<'b>]>;
+
+    /// Called with the input from the first record of each file, before `parse`.
+    /// Returns `true` if the record is a header describing the file rather than
+    /// data, in which case it is not parsed or returned.
+    fn file_header<'b>(&self, _bump: &'b Bump, _input: &FieldVal<'b>) -> bool { false }
 }
 
 pub(crate) fn ty(spec: &crate::config::dataset::ParserKind) -> FieldType {
@@ -19,33 +36,79 @@ pub(crate) fn ty(spec: &crate::config::dataset::ParserKind) -> FieldType {
     match spec {
         Keyword => FieldType::Keyword,
         Number => FieldType::Number,
+        Bool => FieldType::Boolean,
         Dissect { .. } => FieldType::Phrase,
+        Regex { .. } => FieldType::Phrase,
+        Grok(_) => FieldType::Phrase,
         UserAgent => FieldType::Phrase,
         Timestamp { .. } => FieldType::Timestamp,
         Json => FieldType::Phrase,
+        Logfmt | Kv(_) => FieldType::Phrase,
+        Csv(_) => FieldType::Phrase,
+        Syslog => FieldType::Phrase,
+        Url => FieldType::Keyword,
+        Ip | GeoIp(_) => FieldType::Ip,
+        Duration(_) | Bytes(_) => FieldType::Number,
+        ApacheCommon | ApacheCombined | ApacheError | NginxCombined | NginxError => ty(preset::base(spec)),
     }
 }
 
 pub(crate) fn child_fields(spec: &crate::config::dataset::ParserKind) -> Vec<(&str, FieldDefaults)> {
     use crate::config::dataset::ParserKind::*;
     match spec {
-        Keyword | Number => vec![],
+        Keyword | Number | Bool | Duration(_) | Bytes(_) => vec![],
         Dissect { pattern } => dissect::fields(pattern),
+        Regex { pattern } => regex::fields(pattern),
+        Grok(grok) => grok::fields(grok),
         UserAgent => user_agent::fields(),
         Timestamp { .. } => timestamp::fields(),
         Json => vec![],
+        Logfmt | Kv(_) => vec![],
+        Csv(csv) => csv::fields(csv),
+        Syslog => syslog::fields(),
+        Url => url::fields(),
+        Ip => ip::fields(),
+        GeoIp(_) => geoip::fields(),
+        ApacheCommon | ApacheCombined | ApacheError | NginxCombined | NginxError => child_fields(preset::base(spec)),
     }
 }
 
+/// Child fields of a parser with a fixed list of fields and their types
+fn typed_fields(fields: &[(&'static str, FieldType)]) -> Vec<(&'static str, FieldDefaults)> {
+    fields.iter().map(|&(name, ty)| (name, FieldDefaults { ty })).collect()
+}
+
 pub(crate) fn instance<'a>(spec: &'a crate::config::dataset::ParserKind) -> Box<dyn ParserInst + 'a> {
     use crate::config::dataset::ParserKind::*;
     match spec {
         Keyword => Box::new(casts::KeywordInst),
         Number => Box::new(casts::NumberInst),
+        Bool => Box::new(casts::BoolInst),
         Dissect { pattern } => Box::new(dissect::DissectInst(pattern)),
+        Regex { pattern } => Box::new(regex::RegexInst(pattern)),
+        Grok(grok) => Box::new(grok::GrokInst(grok)),
         UserAgent => Box::new(user_agent::UserAgent),
         Timestamp { format, assume_utc} => Box::new(timestamp::Timestamp { format: format.clone(), assume_utc: *assume_utc }),
         Json => Box::new(json::Json::new()),
+        Logfmt => Box::new(kv::KeyValueInst::new(kv::logfmt())),
+        Kv(config) => Box::new(kv::KeyValueInst::new(config)),
+        Csv(config) => Box::new(csv::CsvInst::new(config)),
+        Syslog => Box::new(syslog::Syslog::new()),
+        Url => Box::new(url::UrlInst::new()),
+        Ip => Box::new(ip::Ip),
+        GeoIp(db) => Box::new(geoip::GeoIpInst::new(db)),
+        Duration(config) => Box::new(units::Quantity::from(*config)),
+        Bytes(config) => Box::new(units::Quantity::from(*config)),
+        ApacheCommon | ApacheCombined | ApacheError | NginxCombined | NginxError => instance(preset::base(spec)),
     }
 }
 
+/// Canonical unit of the values produced by a parser, for display
+pub(crate) fn unit(spec: &crate::config::dataset::ParserKind) -> Option<&'static str> {
+    use crate::config::dataset::ParserKind::*;
+    match spec {
+        Duration(_) => Some("s"),
+        Bytes(_) => Some("B"),
+        _ => None,
+    }
+}
@@ -0,0 +1,158 @@
+use std::sync::OnceLock;
+
+use time::format_description::OwnedFormatItem;
+
+use crate::config::dataset::ParserKind;
+
+use super::{regex::RegexPattern, timestamp::TimeFormat};
+
+/// A parser for a well-known log format, along with the parsers for its child
+/// fields that convert them to the appropriate types.
+pub(crate) struct Preset {
+    pub(crate) parser: ParserKind,
+    pub(crate) children: Vec<(&'static str, ParserKind)>,
+}
+
+/// NCSA common log format, as used by Apache's `common` and nginx's `combined` formats.
+/// A request that isn't `METHOD PATH PROTOCOL` is still captured as `request`.
+const COMMON: &str = concat!(
+    r#"^(?P<client>\S+) (?P<ident>\S+) (?P<user>\S+) \[(?P<timestamp>[^\]]+)\] "#,
+    r#""(?P<request>(?P<method>[A-Za-z]+) (?P<path>[^"\s]+)(?: (?P<protocol>[^"\s]+))?|(?:[^"\\]|\\.)*)" "#,
+    r#"(?P<status>\d{3}) (?:(?P<bytes>\d+)|-)"#,
+);
+
+const COMBINED: &str = r#" "(?P<referrer>(?:[^"\\]|\\.)*)" "(?P<user_agent>(?:[^"\\]|\\.)*)""#;
+
+/// `[Wed Oct 11 14:32:52.123456 2000] [core:error] [pid 35708:tid 4328636416] [client 72.15.99.187:53426] AH00128: File does not exist`
+const APACHE_ERROR: &str = concat!(
+    r#"^\[(?P<timestamp>[^\]]+)\] \[(?:(?P<module>[^:\]]+):)?(?P<level>[^\]]+)\]"#,
+    r#"(?: \[pid (?P<pid>\d+)(?::tid (?P<tid>\d+))?\])?(?: \[client (?P<client>[^\]]+)\])? (?P<message>.*)$"#,
+);
+
+/// `2022/10/11 14:32:52 [error] 35708#35708: *12 open() "/favicon.ico" failed`
+const NGINX_ERROR: &str = concat!(
+    r#"^(?P<timestamp>\d{4}/\d\d/\d\d \d\d:\d\d:\d\d) \[(?P<level>\w+)\] (?P<pid>\d+)#(?P<tid>\d+): "#,
+    r#"(?:\*(?P<connection>\d+) )?(?P<message>.*)$"#,
+);
+
+const ACCESS_TIME_FORMAT: &str = "[day]/[month repr:short]/[year]:[hour]:[minute]:[second] [offset_hour sign:mandatory][offset_minute]";
+const NGINX_ERROR_TIME_FORMAT: &str = "[year]/[month]/[day] [hour]:[minute]:[second]";
+
+fn regex(pattern: &str) -> ParserKind {
+    ParserKind::Regex { pattern: RegexPattern::new(pattern).expect("invalid preset pattern") }
+}
+
+fn time_format(format: &str) -> OwnedFormatItem {
+    time::format_description::parse_owned(format).expect("invalid preset time format")
+}
+
+fn timestamp(format: OwnedFormatItem, assume_utc: bool) -> ParserKind {
+    ParserKind::Timestamp { format: TimeFormat::Custom(format), assume_utc }
+}
+
+/// `Wed Oct 11 14:32:52.123456 2000`, where Apache 2.2 omits the fractional seconds
+fn apache_error_time_format() -> OwnedFormatItem {
+    OwnedFormatItem::Compound(Box::new([
+        time_format("[weekday repr:short] [month repr:short] [day] [hour]:[minute]:[second]"),
+        OwnedFormatItem::Optional(Box::new(time_format(".[subsecond]"))),
+        time_format(" [year]"),
+    ]))
+}
+
+fn access_log(pattern: &str) -> Preset {
+    Preset {
+        parser: regex(pattern),
+        children: vec![
+            ("timestamp", timestamp(time_format(ACCESS_TIME_FORMAT), false)),
+            ("status", ParserKind::Number),
+            ("bytes", ParserKind::Bytes(Default::default())),
+        ],
+    }
+}
+
+/// The preset for a preset parser kind, or `None` for other parsers.
+pub(crate) fn get(kind: &ParserKind) -> Option<&'static Preset> {
+    static COMMON_LOG: OnceLock<Preset> = OnceLock::new();
+    static COMBINED_LOG: OnceLock<Preset> = OnceLock::new();
+    static APACHE_ERROR_LOG: OnceLock<Preset> = OnceLock::new();
+    static NGINX_ERROR_LOG: OnceLock<Preset> = OnceLock::new();
+
+    match kind {
+        ParserKind::ApacheCommon => Some(COMMON_LOG.get_or_init(|| access_log(COMMON))),
+        ParserKind::ApacheCombined | ParserKind::NginxCombined => {
+            Some(COMBINED_LOG.get_or_init(|| access_log(&format!("{COMMON}{COMBINED}"))))
+        }
+        ParserKind::ApacheError => Some(APACHE_ERROR_LOG.get_or_init(|| Preset {
+            parser: regex(APACHE_ERROR),
+            children: vec![
+                // Apache writes local time without an offset
+                ("timestamp", timestamp(apache_error_time_format(), true)),
+                ("pid", ParserKind::Number),
+            ],
+        })),
+        ParserKind::NginxError => Some(NGINX_ERROR_LOG.get_or_init(|| Preset {
+            parser: regex(NGINX_ERROR),
+            children: vec![
+                ("timestamp", timestamp(time_format(NGINX_ERROR_TIME_FORMAT), true)),
+                ("pid", ParserKind::Number),
+                ("connection", ParserKind::Number),
+            ],
+        })),
+        _ => None,
+    }
+}
+
+/// The parser that a preset parser kind uses for the field itself.
+pub(crate) fn base(kind: &ParserKind) -> &'static ParserKind {
+    &get(kind).expect("not a preset parser").parser
+}
+
+#[test]
+fn test() {
+    use bumpalo::Bump;
+    use time::macros::datetime;
+    use crate::query::FieldVal;
+
+    /// Parse `line` with a preset and its child parsers, returning the requested child values.
+    fn parse<'b>(bump: &'b Bump, kind: ParserKind, line: &'b str, fields: &[&str]) -> Option<Vec<FieldVal<'b>>> {
+        let preset = get(&kind).unwrap();
+        let mut inst = super::instance(&preset.parser);
+        let indexes: Vec<usize> = fields.iter().map(|f| inst.require_field(f).unwrap()).collect();
+        let vals = inst.parse(bump, &mut FieldVal::String(line))?;
+
+        Some(fields.iter().zip(indexes).map(|(field, i)| {
+            let mut v = vals[i];
+            if let Some((_, child)) = preset.children.iter().find(|(name, _)| name == field) {
+                if v.exists() {
+                    super::instance(child).parse(bump, &mut v).unwrap();
+                }
+            }
+            v
+        }).collect())
+    }
+
+    let bump = Bump::new();
+    use FieldVal::{String as S, Number as N, Time as T, Null};
+
+    let fields = ["client", "timestamp", "method", "path", "protocol", "status", "bytes", "user_agent"];
+    assert_eq!(parse(&bump, ParserKind::NginxCombined,
+        r#"1.2.3.4 - - [30/Apr/1998:22:00:52 +0000] "GET /some/path?a=b HTTP/1.0" 200 3171 "-" "Mozilla/5.0 (\"quoted\")""#, &fields),
+        Some(vec![S("1.2.3.4"), T(datetime!(1998-04-30 22:00:52 UTC)), S("GET"), S("/some/path?a=b"), S("HTTP/1.0"), N(200.0), N(3171.0), S(r#"Mozilla/5.0 (\"quoted\")"#)]));
+
+    let fields = ["request", "method", "status", "bytes"];
+    assert_eq!(parse(&bump, ParserKind::ApacheCommon, r#"::1 - bob [01/Jan/2020:00:00:00 -0700] "\x16\x03" 400 -"#, &fields),
+        Some(vec![S(r#"\x16\x03"#), Null, N(400.0), Null]));
+    assert_eq!(parse(&bump, ParserKind::ApacheCommon, "not an access log", &fields), None);
+
+    let fields = ["timestamp", "module", "level", "pid", "client", "message"];
+    assert_eq!(parse(&bump, ParserKind::ApacheError,
+        "[Wed Oct 11 14:32:52.123456 2000] [core:error] [pid 35708:tid 4328636416] [client 72.15.99.187:53426] AH00128: File does not exist",
+        &fields),
+        Some(vec![T(datetime!(2000-10-11 14:32:52.123456 UTC)), S("core"), S("error"), N(35708.0), S("72.15.99.187:53426"), S("AH00128: File does not exist")]));
+    assert_eq!(parse(&bump, ParserKind::ApacheError, "[Wed Oct 11 14:32:52 2000] [error] [client 127.0.0.1] denied", &fields),
+        Some(vec![T(datetime!(2000-10-11 14:32:52 UTC)), Null, S("error"), Null, S("127.0.0.1"), S("denied")]));
+
+    let fields = ["timestamp", "level", "pid", "connection", "message"];
+    assert_eq!(parse(&bump, ParserKind::NginxError, r#"2022/10/11 14:32:52 [error] 35708#35708: *12 open() "/favicon.ico" failed"#, &fields),
+        Some(vec![T(datetime!(2022-10-11 14:32:52 UTC)), S("error"), N(35708.0), N(12.0), S(r#"open() "/favicon.ico" failed"#)]));
+}
@@ -0,0 +1,93 @@
+use bumpalo::Bump;
+use bumpalo::collections::Vec as BVec;
+use serde::{Deserialize, Deserializer};
+
+use crate::{query::FieldVal, FieldDefaults, api::fields::FieldType};
+
+use super::ParserInst;
+
+#[derive(Clone)]
+pub struct RegexPattern {
+    regex: ::regex::Regex,
+
+    /// Names of the capture groups, in order
+    fields: Vec<String>,
+
+    /// Index of the capture group for each field
+    groups: Vec<usize>,
+}
+
+impl RegexPattern {
+    pub fn new(pattern: &str) -> Result<RegexPattern, ::regex::Error> {
+        let regex = ::regex::Regex::new(pattern)?;
+
+        let (groups, fields) = regex.capture_names().enumerate()
+            .filter_map(|(i, name)| Some((i, name?.to_owned())))
+            .unzip();
+
+        Ok(RegexPattern { regex, fields, groups })
+    }
+
+    fn parse_with<'a>(&self, s: &'a str, mut push: impl FnMut(Option<&'a str>)) -> bool {
+        let mut locs = self.regex.capture_locations();
+        if self.regex.captures_read(&mut locs, s).is_none() {
+            return false;
+        }
+
+        for &group in &self.groups {
+            push(locs.get(group).map(|(start, end)| &s[start..end]));
+        }
+
+        true
+    }
+
+    #[cfg(test)]
+    fn parse<'a>(&self, s: &'a str) -> Option<Vec<Option<&'a str>>> {
+        let mut results = Vec::new();
+        if self.parse_with(s, |v| results.push(v)) {
+            Some(results)
+        } else { None }
+    }
+}
+
+impl<'de> Deserialize<'de> for RegexPattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        RegexPattern::new(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+pub(crate) fn fields(s: &RegexPattern) -> Vec<(&str, FieldDefaults)> {
+    s.fields.iter().map(|x| (&x[..], FieldDefaults { ty: FieldType::Keyword })).collect()
+}
+
+pub(crate) struct RegexInst<'req>(pub(crate) &'req RegexPattern);
+
+impl<'req> ParserInst for RegexInst<'req> {
+    fn require_field(&mut self, field: &str) -> Option<usize> {
+        self.0.fields.iter().position(|x| x == field)
+    }
+
+    fn parse<'b>(&self, bump: &'b Bump, input: &mut FieldVal<'b>) -> Option<&'b mut [FieldVal<'b>]> {
+        let input = input.as_str().unwrap_or_default();
+        let mut results = BVec::with_capacity_in(self.0.fields.len(), bump);
+        if self.0.parse_with(input, |v| results.push(v.map_or(FieldVal::Null, |v| FieldVal::String(bump.alloc_str(v))))) {
+            Some(results.into_bump_slice_mut())
+        } else { None }
+    }
+}
+
+#[test]
+fn test() {
+    let p = RegexPattern::new(r"^(?P<level>[A-Z]+)\s+(?:\[(?P<thread>[^\]]*)\]\s+)?(?P<msg>.*)$").unwrap();
+    assert_eq!(p.fields, vec!["level", "thread", "msg"]);
+    assert_eq!(p.parse("INFO   [main] started"), Some(vec![Some("INFO"), Some("main"), Some("started")]));
+    assert_eq!(p.parse("WARN low disk"), Some(vec![Some("WARN"), None, Some("low disk")]));
+    assert_eq!(p.parse("lowercase"), None);
+
+    let unnamed = RegexPattern::new(r"(\d+)-(?P<b>\d+)").unwrap();
+    assert_eq!(unnamed.fields, vec!["b"]);
+    assert_eq!(unnamed.parse("x 12-34 y"), Some(vec![Some("34")]));
+
+    assert!(RegexPattern::new("(?P<unclosed>").is_err());
+}
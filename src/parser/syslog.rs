@@ -0,0 +1,274 @@
+use bumpalo::Bump;
+use time::{OffsetDateTime, Date, Month, Time, Duration, format_description::well_known::Rfc3339};
+
+use crate::{query::FieldVal, FieldDefaults, api::fields::FieldType};
+
+use super::ParserInst;
+
+const FIELDS: &[(&str, FieldType)] = &[
+    ("priority", FieldType::Number),
+    ("facility", FieldType::Keyword),
+    ("severity", FieldType::Keyword),
+    ("timestamp", FieldType::Timestamp),
+    ("hostname", FieldType::Keyword),
+    ("app_name", FieldType::Keyword),
+    ("procid", FieldType::Keyword),
+    ("msgid", FieldType::Keyword),
+    ("structured_data", FieldType::Phrase),
+    ("message", FieldType::Phrase),
+];
+
+const FACILITIES: &[&str] = &[
+    "kern", "user", "mail", "daemon", "auth", "syslog", "lpr", "news",
+    "uucp", "cron", "authpriv", "ftp", "ntp", "security", "console", "clock",
+    "local0", "local1", "local2", "local3", "local4", "local5", "local6", "local7",
+];
+
+const SEVERITIES: &[&str] = &["emerg", "alert", "crit", "err", "warning", "notice", "info", "debug"];
+
+const MONTHS: &[&str] = &["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+pub(crate) fn fields() -> Vec<(&'static str, FieldDefaults)> {
+    super::typed_fields(FIELDS)
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Record<'a> {
+    priority: Option<u8>,
+    timestamp: Option<OffsetDateTime>,
+    hostname: Option<&'a str>,
+    app_name: Option<&'a str>,
+    procid: Option<&'a str>,
+    msgid: Option<&'a str>,
+    structured_data: Option<&'a str>,
+    message: Option<&'a str>,
+}
+
+/// Parse an RFC 5424 or RFC 3164 (BSD) syslog line. The priority is optional
+/// because local log files such as `/var/log/syslog` omit it.
+fn parse(s: &str, now: OffsetDateTime) -> Option<Record<'_>> {
+    let (priority, rest) = match s.strip_prefix('<') {
+        Some(rest) => {
+            let (pri, rest) = rest.split_once('>')?;
+            let pri = pri.parse::<u8>().ok().filter(|&p| p < 192)?;
+            (Some(pri), rest)
+        }
+        None => (None, s),
+    };
+
+    let is_rfc5424 = rest.split_once(' ')
+        .is_some_and(|(version, _)| !version.is_empty() && version.bytes().all(|b| b.is_ascii_digit()));
+
+    let record = if is_rfc5424 { parse_rfc5424(rest)? } else { parse_rfc3164(rest, now)? };
+    Some(Record { priority, ..record })
+}
+
+/// `VERSION TIMESTAMP HOSTNAME APP-NAME PROCID MSGID STRUCTURED-DATA [MSG]`
+fn parse_rfc5424(s: &str) -> Option<Record<'_>> {
+    let (_version, s) = s.split_once(' ')?;
+    let (timestamp, s) = s.split_once(' ')?;
+    let (hostname, s) = s.split_once(' ')?;
+    let (app_name, s) = s.split_once(' ')?;
+    let (procid, s) = s.split_once(' ')?;
+    let (msgid, s) = s.split_once(' ')?;
+
+    let timestamp = match nil(timestamp) {
+        Some(t) => Some(OffsetDateTime::parse(t, &Rfc3339).ok()?),
+        None => None,
+    };
+
+    let sd_len = structured_data_len(s)?;
+    let structured_data = nil(&s[..sd_len]);
+    let message = s[sd_len..].strip_prefix(' ')
+        .map(|m| m.trim_start_matches('\u{feff}'))
+        .filter(|m| !m.is_empty());
+
+    Some(Record {
+        priority: None,
+        timestamp,
+        hostname: nil(hostname),
+        app_name: nil(app_name),
+        procid: nil(procid),
+        msgid: nil(msgid),
+        structured_data,
+        message,
+    })
+}
+
+/// Length of the structured data at the start of `s`: either `-` or a sequence
+/// of `[id name="value" ...]` elements, where values may contain escaped `"`, `\` and `]`.
+fn structured_data_len(s: &str) -> Option<usize> {
+    if s.starts_with('-') {
+        return Some(1);
+    }
+
+    let mut len = 0;
+    while s[len..].starts_with('[') {
+        let mut in_value = false;
+        let mut chars = s[len..].char_indices();
+        let end = loop {
+            match chars.next()? {
+                (_, '\\') if in_value => { chars.next(); }
+                (_, '"') => in_value = !in_value,
+                (i, ']') if !in_value => break i + 1,
+                _ => {}
+            }
+        };
+        len += end;
+    }
+
+    (len > 0).then_some(len)
+}
+
+/// `TIMESTAMP HOSTNAME TAG[PID]: MSG`, where the timestamp is either
+/// `Mmm dd hh:mm:ss` in UTC or RFC 3339 as written by rsyslog.
+fn parse_rfc3164(s: &str, now: OffsetDateTime) -> Option<Record<'_>> {
+    let (timestamp, s) = match s.get(..15).and_then(|t| bsd_timestamp(t, now)) {
+        Some(t) => (t, &s[15..]),
+        None => {
+            let (t, rest) = s.split_once(' ')?;
+            (OffsetDateTime::parse(t, &Rfc3339).ok()?, rest)
+        }
+    };
+
+    let s = s.trim_start_matches(' ');
+    let (hostname, s) = s.split_once(' ').unwrap_or((s, ""));
+    if hostname.is_empty() { return None }
+
+    let mut record = Record {
+        timestamp: Some(timestamp),
+        hostname: Some(hostname),
+        ..Default::default()
+    };
+
+    let tag_end = s.find([':', '[', ' ']).unwrap_or(s.len());
+    let (app_name, procid, message) = match s[tag_end..].chars().next() {
+        Some('[') => {
+            let (procid, rest) = s[tag_end + 1..].split_once(']')?;
+            (Some(&s[..tag_end]), Some(procid), rest.strip_prefix(':').unwrap_or(rest))
+        }
+        Some(':') => (Some(&s[..tag_end]), None, &s[tag_end + 1..]),
+        _ => (None, None, s),
+    };
+
+    record.app_name = app_name.filter(|a| !a.is_empty());
+    record.procid = procid;
+    record.message = Some(message.strip_prefix(' ').unwrap_or(message)).filter(|m| !m.is_empty());
+    Some(record)
+}
+
+/// Parse `Mmm dd hh:mm:ss`, choosing the most recent year that doesn't place it in the future.
+fn bsd_timestamp(s: &str, now: OffsetDateTime) -> Option<OffsetDateTime> {
+    if !s.is_ascii() { return None }
+    let month = MONTHS.iter().position(|&m| m == &s[..3])?;
+    let month = Month::try_from(month as u8 + 1).ok()?;
+    if &s[3..4] != " " || &s[6..7] != " " { return None }
+    let day: u8 = s[4..6].trim_start().parse().ok()?;
+
+    let mut hms = s[7..].splitn(3, ':').map(|x| x.parse::<u8>().ok());
+    let time = Time::from_hms(hms.next()??, hms.next()??, hms.next()??).ok()?;
+
+    let at_year = |year| Some(Date::from_calendar_date(year, month, day).ok()?.with_time(time).assume_utc());
+    match at_year(now.year()) {
+        Some(t) if t <= now + Duration::DAY => Some(t),
+        _ => at_year(now.year() - 1),
+    }
+}
+
+/// RFC 5424 uses `-` for missing values
+fn nil(s: &str) -> Option<&str> {
+    Some(s).filter(|&s| s != "-")
+}
+
+pub(crate) struct Syslog {
+    /// Used to infer the year of RFC 3164 timestamps
+    now: OffsetDateTime,
+}
+
+impl Syslog {
+    pub(crate) fn new() -> Self {
+        Syslog { now: OffsetDateTime::now_utc() }
+    }
+}
+
+impl ParserInst for Syslog {
+    fn require_field(&mut self, field: &str) -> Option<usize> {
+        FIELDS.iter().position(|&(x, _)| x == field)
+    }
+
+    fn parse<'b>(&self, bump: &'b Bump, input: &mut FieldVal<'b>) -> Option<&'b mut [FieldVal<'b>]> {
+        let FieldVal::String(s) = *input else { return None };
+        let r = parse(s, self.now)?;
+
+        let string = |v: Option<&'b str>| v.map_or(FieldVal::Null, FieldVal::String);
+        let facility = r.priority.map(|p| FACILITIES[usize::from(p / 8)]);
+        let severity = r.priority.map(|p| SEVERITIES[usize::from(p % 8)]);
+
+        Some(bump.alloc([
+            r.priority.map_or(FieldVal::Null, |p| FieldVal::Number(p.into())),
+            string(facility),
+            string(severity),
+            r.timestamp.map_or(FieldVal::Null, FieldVal::Time),
+            string(r.hostname),
+            string(r.app_name),
+            string(r.procid),
+            string(r.msgid),
+            string(r.structured_data),
+            string(r.message),
+        ]))
+    }
+}
+
+#[test]
+fn test() {
+    use time::macros::datetime;
+    let now = datetime!(2022-03-01 12:00 UTC);
+
+    assert_eq!(parse("<34>Oct 11 22:14:15 mymachine su: 'su root' failed for lonvick on /dev/pts/8", now), Some(Record {
+        priority: Some(34),
+        timestamp: Some(datetime!(2021-10-11 22:14:15 UTC)),
+        hostname: Some("mymachine"),
+        app_name: Some("su"),
+        message: Some("'su root' failed for lonvick on /dev/pts/8"),
+        ..Default::default()
+    }));
+
+    assert_eq!(parse("Mar  1 08:00:01 web1 CRON[4242]: (root) CMD (run-parts)", now), Some(Record {
+        timestamp: Some(datetime!(2022-03-01 08:00:01 UTC)),
+        hostname: Some("web1"),
+        app_name: Some("CRON"),
+        procid: Some("4242"),
+        message: Some("(root) CMD (run-parts)"),
+        ..Default::default()
+    }));
+
+    assert_eq!(parse("2022-02-28T23:59:59.5+01:00 web1 kernel: [ 12.5] eth0: link up", now), Some(Record {
+        timestamp: Some(datetime!(2022-02-28 23:59:59.5 +01:00)),
+        hostname: Some("web1"),
+        app_name: Some("kernel"),
+        message: Some("[ 12.5] eth0: link up"),
+        ..Default::default()
+    }));
+
+    assert_eq!(parse(r#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3" eventSource="App\"lication\]"][x@1 a="b"] An application event"#, now), Some(Record {
+        priority: Some(165),
+        timestamp: Some(datetime!(2003-10-11 22:14:15.003 UTC)),
+        hostname: Some("mymachine.example.com"),
+        app_name: Some("evntslog"),
+        procid: None,
+        msgid: Some("ID47"),
+        structured_data: Some(r#"[exampleSDID@32473 iut="3" eventSource="App\"lication\]"][x@1 a="b"]"#),
+        message: Some("An application event"),
+    }));
+
+    assert_eq!(parse("<13>1 - - - - - -", now), Some(Record { priority: Some(13), ..Default::default() }));
+    assert_eq!(parse("<13>1 - host app 1 - [unterminated", now), None);
+    assert_eq!(parse("<999>Oct 11 22:14:15 host app: x", now), None);
+    assert_eq!(parse("not syslog", now), None);
+
+    let bump = Bump::new();
+    let mut inst = Syslog::new();
+    assert_eq!(inst.require_field("severity"), Some(2));
+    let vals = inst.parse(&bump, &mut FieldVal::String("<165>1 - - - - - - hi")).unwrap();
+    assert_eq!(&vals[..3], &[FieldVal::Number(165.0), FieldVal::String("local4"), FieldVal::String("notice")]);
+}
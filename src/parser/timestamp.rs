@@ -55,7 +55,7 @@ impl ParserInst for Timestamp {
         None
     }
 
-    fn parse<'b>(&self, _bump: &'b Bump, input: &mut FieldVal) -> &'b mut [FieldVal<'b>] {
+    fn parse<'b>(&self, _bump: &'b Bump, input: &mut FieldVal) -> Option<&'b mut [FieldVal<'b>]> {
         match input {
             FieldVal::String(s) => {
                 let t = if self.assume_utc {
@@ -64,12 +64,10 @@ impl ParserInst for Timestamp {
                     OffsetDateTime::parse(s, self.format.as_format())
                 };
 
-                if let Ok(t) = t {
-                    *input = FieldVal::Time(t);
-                }                
+                *input = FieldVal::Time(t.ok()?);
             },
-            FieldVal::Null | FieldVal::Number(_) | FieldVal::Time(_) | FieldVal::Map(_) => {},
+            FieldVal::Null | FieldVal::Number(_) | FieldVal::Bool(_) | FieldVal::Time(_) | FieldVal::Ip(_) | FieldVal::Map(_) | FieldVal::Array(_) => {},
         }
-        &mut []
+        Some(&mut [])
     }
 }
\ No newline at end of file
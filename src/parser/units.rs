@@ -0,0 +1,174 @@
+use bumpalo::Bump;
+use serde::Deserialize;
+
+use crate::query::FieldVal;
+
+use super::ParserInst;
+
+/// Duration units in seconds
+const DURATION_UNITS: &[(&str, f64)] = &[
+    ("ns", 1e-9), ("us", 1e-6), ("µs", 1e-6), ("μs", 1e-6), ("ms", 1e-3),
+    ("s", 1.0), ("sec", 1.0), ("secs", 1.0), ("second", 1.0), ("seconds", 1.0),
+    ("m", 60.0), ("min", 60.0), ("mins", 60.0), ("minute", 60.0), ("minutes", 60.0),
+    ("h", 3600.0), ("hr", 3600.0), ("hrs", 3600.0), ("hour", 3600.0), ("hours", 3600.0),
+    ("d", 86400.0), ("day", 86400.0), ("days", 86400.0),
+    ("w", 604800.0), ("week", 604800.0), ("weeks", 604800.0),
+];
+
+/// Size units in bytes. Single-letter suffixes are binary, as used by `ls -h`
+/// and most server configuration, while `kB`, `MB`... are decimal.
+const BYTE_UNITS: &[(&str, f64)] = &[
+    ("b", 1.0), ("byte", 1.0), ("bytes", 1.0),
+    ("k", 1024.0), ("kb", 1e3), ("kib", 1024.0),
+    ("m", 1048576.0), ("mb", 1e6), ("mib", 1048576.0),
+    ("g", 1073741824.0), ("gb", 1e9), ("gib", 1073741824.0),
+    ("t", 1099511627776.0), ("tb", 1e12), ("tib", 1099511627776.0),
+    ("p", 1125899906842624.0), ("pb", 1e15), ("pib", 1125899906842624.0),
+];
+
+/// Parses a quantity such as `1.5s`, `3m20s` or `2.1 GB` and converts it to a canonical unit.
+#[derive(Clone, Copy)]
+pub struct Quantity {
+    units: &'static [(&'static str, f64)],
+
+    /// Factor for numbers without a unit
+    assume: f64,
+}
+
+#[derive(Deserialize)]
+struct QuantityConfig {
+    /// Unit of numbers without a unit, defaulting to the canonical unit
+    assume_unit: Option<String>,
+}
+
+impl Quantity {
+    fn new(units: &'static [(&'static str, f64)], config: QuantityConfig) -> Result<Quantity, String> {
+        let assume = match config.assume_unit {
+            Some(unit) => lookup(units, &unit).ok_or_else(|| format!("unknown unit `{unit}`"))?,
+            None => 1.0,
+        };
+        Ok(Quantity { units, assume })
+    }
+
+    fn value(&self, s: &str) -> Option<f64> {
+        let mut rest = s.trim();
+        let mut total = 0.0;
+        let mut components = 0;
+
+        while !rest.is_empty() {
+            let num_len = rest.find(|c: char| !(c.is_ascii_digit() || c == '.' || (c == '-' && components == 0)))
+                .unwrap_or(rest.len());
+            let value: f64 = rest[..num_len].parse().ok()?;
+            rest = rest[num_len..].trim_start();
+
+            let unit_len = rest.find(|c: char| !c.is_alphabetic()).unwrap_or(rest.len());
+            let factor = if unit_len == 0 {
+                // A bare number is only valid on its own
+                if components > 0 || !rest.is_empty() { return None }
+                self.assume
+            } else {
+                lookup(self.units, &rest[..unit_len])?
+            };
+            rest = rest[unit_len..].trim_start();
+
+            total += value * factor;
+            components += 1;
+        }
+
+        (components > 0).then_some(total)
+    }
+}
+
+fn lookup(units: &[(&str, f64)], unit: &str) -> Option<f64> {
+    units.iter().find(|(u, _)| u.eq_ignore_ascii_case(unit)).map(|&(_, factor)| factor)
+}
+
+/// A duration, normalized to seconds
+#[derive(Clone, Copy, Deserialize)]
+#[serde(try_from = "QuantityConfig")]
+pub struct Duration(Quantity);
+
+impl TryFrom<QuantityConfig> for Duration {
+    type Error = String;
+
+    fn try_from(config: QuantityConfig) -> Result<Self, Self::Error> {
+        Quantity::new(DURATION_UNITS, config).map(Duration)
+    }
+}
+
+/// A size, normalized to bytes
+#[derive(Clone, Copy, Deserialize)]
+#[serde(try_from = "QuantityConfig")]
+pub struct Bytes(Quantity);
+
+impl TryFrom<QuantityConfig> for Bytes {
+    type Error = String;
+
+    fn try_from(config: QuantityConfig) -> Result<Self, Self::Error> {
+        Quantity::new(BYTE_UNITS, config).map(Bytes)
+    }
+}
+
+impl Default for Bytes {
+    fn default() -> Self {
+        Bytes(Quantity { units: BYTE_UNITS, assume: 1.0 })
+    }
+}
+
+impl From<Duration> for Quantity {
+    fn from(d: Duration) -> Self { d.0 }
+}
+
+impl From<Bytes> for Quantity {
+    fn from(b: Bytes) -> Self { b.0 }
+}
+
+impl ParserInst for Quantity {
+    fn require_field(&mut self, _field: &str) -> Option<usize> {
+        None
+    }
+
+    fn parse<'b>(&self, _bump: &'b Bump, input: &mut FieldVal<'b>) -> Option<&'b mut [FieldVal<'b>]> {
+        match *input {
+            FieldVal::String(s) => *input = FieldVal::Number(self.value(s)?),
+            FieldVal::Number(n) => *input = FieldVal::Number(n * self.assume),
+            _ => {}
+        }
+        Some(&mut [])
+    }
+}
+
+#[test]
+fn test() {
+    let duration = |toml_src: &str| Quantity::from(toml::from_str::<Duration>(toml_src).unwrap());
+    let bytes = |toml_src: &str| Quantity::from(toml::from_str::<Bytes>(toml_src).unwrap());
+
+    let d = duration("");
+    assert_eq!(d.value("12ms"), Some(0.012));
+    assert_eq!(d.value("1.5s"), Some(1.5));
+    assert_eq!(d.value("3m20s"), Some(200.0));
+    assert_eq!(d.value("1h 30m"), Some(5400.0));
+    assert_eq!(d.value(" 250 µs "), Some(0.00025));
+    assert_eq!(d.value("42"), Some(42.0));
+    assert_eq!(d.value("-5s"), Some(-5.0));
+    assert_eq!(d.value("3m20"), None);
+    assert_eq!(d.value("12 parsecs"), None);
+    assert_eq!(d.value(""), None);
+    assert_eq!(d.value("s"), None);
+
+    assert_eq!(duration(r#"assume_unit = "ms""#).value("1500"), Some(1.5));
+    assert!(toml::from_str::<Duration>(r#"assume_unit = "KiB""#).is_err());
+
+    let b = bytes("");
+    assert_eq!(b.value("512KiB"), Some(524288.0));
+    assert_eq!(b.value("2.1 GB"), Some(2.1e9));
+    assert_eq!(b.value("4k"), Some(4096.0));
+    assert_eq!(b.value("100 bytes"), Some(100.0));
+    assert_eq!(b.value("3171"), Some(3171.0));
+    assert_eq!(b.value("-"), None);
+
+    let bump = Bump::new();
+    let mut input = FieldVal::Number(2.0);
+    bytes(r#"assume_unit = "MiB""#).parse(&bump, &mut input).unwrap();
+    assert_eq!(input, FieldVal::Number(2097152.0));
+}
@@ -0,0 +1,270 @@
+use bumpalo::Bump;
+use bumpalo::collections::{String as BString, Vec as BVec};
+use indexmap::IndexMap;
+
+use crate::{query::FieldVal, FieldDefaults, api::fields::FieldType};
+
+use super::ParserInst;
+
+const FIELDS: &[(&str, FieldType)] = &[
+    ("scheme", FieldType::Keyword),
+    ("host", FieldType::Keyword),
+    ("port", FieldType::Number),
+    ("path", FieldType::Keyword),
+    ("extension", FieldType::Keyword),
+    ("query", FieldType::Keyword),
+    ("fragment", FieldType::Keyword),
+];
+
+pub(crate) fn fields() -> Vec<(&'static str, FieldDefaults)> {
+    super::typed_fields(FIELDS)
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Url<'a> {
+    scheme: Option<&'a str>,
+    host: Option<&'a str>,
+    port: Option<u16>,
+
+    /// Path as it appears in the URL, without decoding `%XX` escapes, so that an
+    /// encoded `/` stays distinct from a separator. The `segments.N` fields are decoded.
+    path: &'a str,
+    query: Option<&'a str>,
+    fragment: Option<&'a str>,
+}
+
+impl<'a> Url<'a> {
+    /// Parse an absolute URL, a scheme-relative `//host/path` or an HTTP request target.
+    fn parse(s: &'a str) -> Option<Url<'a>> {
+        if s.is_empty() { return None }
+
+        let (s, fragment) = match s.split_once('#') {
+            Some((s, fragment)) => (s, Some(fragment)),
+            None => (s, None),
+        };
+
+        let (s, query) = match s.split_once('?') {
+            Some((s, query)) => (s, Some(query)),
+            None => (s, None),
+        };
+
+        let (scheme, with_authority) = match s.split_once("://") {
+            Some((scheme, rest)) if is_scheme(scheme) => (Some(scheme), Some(rest)),
+            _ => (None, s.strip_prefix("//")),
+        };
+
+        let mut url = Url { scheme, query, fragment, ..Default::default() };
+
+        match with_authority {
+            Some(s) => {
+                let (authority, path) = s.split_at(s.find('/').unwrap_or(s.len()));
+                let host_port = authority.rsplit_once('@').map_or(authority, |(_userinfo, host)| host);
+
+                let (host, port) = match host_port.rsplit_once(':') {
+                    Some((host, port)) if !port.contains(']') => (host, Some(port.parse().ok()?)),
+                    _ => (host_port, None),
+                };
+
+                url.host = Some(host.trim_start_matches('[').trim_end_matches(']')).filter(|h| !h.is_empty());
+                url.port = port;
+                url.path = if path.is_empty() { "/" } else { path };
+            }
+            None => url.path = s,
+        }
+
+        Some(url)
+    }
+
+    fn segments(&self) -> impl Iterator<Item = &'a str> {
+        self.path.split('/').filter(|s| !s.is_empty())
+    }
+
+    /// Extension of the last path segment, excluding a leading dot as in `.well-known`
+    fn extension(&self) -> Option<&'a str> {
+        if self.path.ends_with('/') { return None }
+        let (name, ext) = self.segments().last()?.rsplit_once('.')?;
+        Some(ext).filter(|_| !name.is_empty())
+    }
+
+    /// Decoded value of the first query parameter named `name`
+    fn param<'b>(&self, bump: &'b Bump, name: &str) -> Option<&'b str> where 'a: 'b {
+        self.query?.split('&').find_map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (decode(bump, key) == name).then(|| decode(bump, value))
+        })
+    }
+}
+
+fn is_scheme(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic())
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
+
+/// Decode `%XX` escapes and `+` as space, leaving invalid escapes as-is.
+fn decode<'a: 'b, 'b>(bump: &'b Bump, s: &'a str) -> &'b str {
+    if !s.contains(['%', '+']) { return s }
+
+    let mut bytes = BVec::with_capacity_in(s.len(), bump);
+    let mut rest = s.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        let escaped = tail.get(..2)
+            .filter(|_| b == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+
+        match escaped {
+            Some(decoded) => {
+                bytes.push(decoded);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(if b == b'+' { b' ' } else { b });
+                rest = tail;
+            }
+        }
+    }
+
+    match std::str::from_utf8(bytes.into_bump_slice()) {
+        Ok(s) => s,
+        Err(_) => BString::from_utf8_lossy_in(s.as_bytes(), bump).into_bump_str(),
+    }
+}
+
+/// One of the fixed `FIELDS`
+enum Component {
+    Scheme,
+    Host,
+    Port,
+    Path,
+    Extension,
+    Query,
+    Fragment,
+}
+
+impl Component {
+    fn from_name(name: &str) -> Option<Component> {
+        Some(match name {
+            "scheme" => Component::Scheme,
+            "host" => Component::Host,
+            "port" => Component::Port,
+            "path" => Component::Path,
+            "extension" => Component::Extension,
+            "query" => Component::Query,
+            "fragment" => Component::Fragment,
+            _ => return None,
+        })
+    }
+}
+
+enum Part {
+    Component(Component),
+    Segment(usize),
+    Param(String),
+}
+
+pub(crate) struct UrlInst {
+    fields: IndexMap<String, Part>,
+}
+
+impl UrlInst {
+    pub(crate) fn new() -> Self {
+        UrlInst { fields: IndexMap::new() }
+    }
+}
+
+impl ParserInst for UrlInst {
+    fn require_field(&mut self, field: &str) -> Option<usize> {
+        if let Some(i) = self.fields.get_index_of(field) {
+            return Some(i);
+        }
+
+        let part = if let Some(component) = Component::from_name(field) {
+            Part::Component(component)
+        } else if let Some(param) = field.strip_prefix("query.") {
+            Part::Param(param.to_owned())
+        } else if let Some(n) = field.strip_prefix("segments.") {
+            Part::Segment(n.parse().ok()?)
+        } else {
+            return None;
+        };
+
+        Some(self.fields.insert_full(field.to_owned(), part).0)
+    }
+
+    fn parse<'b>(&self, bump: &'b Bump, input: &mut FieldVal<'b>) -> Option<&'b mut [FieldVal<'b>]> {
+        let FieldVal::String(s) = *input else { return None };
+        let url = Url::parse(s)?;
+
+        let string = |v: Option<&'b str>| v.map_or(FieldVal::Null, FieldVal::String);
+
+        Some(bump.alloc_slice_fill_iter(self.fields.values().map(|part| match part {
+            Part::Component(Component::Scheme) => string(url.scheme),
+            Part::Component(Component::Host) => string(url.host),
+            Part::Component(Component::Port) => url.port.map_or(FieldVal::Null, |p| FieldVal::Number(p.into())),
+            Part::Component(Component::Path) => FieldVal::String(url.path),
+            Part::Component(Component::Extension) => string(url.extension()),
+            Part::Component(Component::Query) => string(url.query),
+            Part::Component(Component::Fragment) => string(url.fragment),
+            Part::Segment(n) => string(url.segments().nth(*n).map(|s| decode(bump, s))),
+            Part::Param(name) => string(url.param(bump, name)),
+        })))
+    }
+}
+
+#[test]
+fn test() {
+    assert_eq!(Url::parse("https://user:pw@Example.com:8443/a/b/report.tar.gz?x=1&utm_source=news#top"), Some(Url {
+        scheme: Some("https"),
+        host: Some("Example.com"),
+        port: Some(8443),
+        path: "/a/b/report.tar.gz",
+        query: Some("x=1&utm_source=news"),
+        fragment: Some("top"),
+    }));
+    assert_eq!(Url::parse("http://[::1]"), Some(Url { scheme: Some("http"), host: Some("::1"), path: "/", ..Default::default() }));
+    assert_eq!(Url::parse("//cdn.example.com/x.js"), Some(Url { host: Some("cdn.example.com"), path: "/x.js", ..Default::default() }));
+    assert_eq!(Url::parse("/search?q=a+b%20c&q=second&empty&bad=%zz"), Some(Url {
+        path: "/search", query: Some("q=a+b%20c&q=second&empty&bad=%zz"), ..Default::default()
+    }));
+    assert_eq!(Url::parse("http://host:notaport/"), None);
+    assert_eq!(Url::parse(""), None);
+
+    assert_eq!(Url::parse("/a/b.tar.gz").unwrap().extension(), Some("gz"));
+    assert_eq!(Url::parse("/.well-known").unwrap().extension(), None);
+    assert_eq!(Url::parse("/dir.d/").unwrap().extension(), None);
+
+    let bump = Bump::new();
+    let mut inst = UrlInst::new();
+    let fields = ["path", "query.q", "query.empty", "query.bad", "query.missing", "segments.0", "segments.5", "port", "extension"];
+    for (i, field) in fields.iter().enumerate() {
+        assert_eq!(inst.require_field(field), Some(i));
+    }
+    assert_eq!(inst.require_field("segments.x"), None);
+    assert_eq!(inst.require_field("bogus"), None);
+    assert!(FIELDS.iter().all(|(name, _)| Component::from_name(name).is_some()));
+
+    let vals = inst.parse(&bump, &mut FieldVal::String("/search%20page/?q=a+b%20c&q=second&empty&bad=%zz")).unwrap();
+    assert_eq!(vals, &[
+        FieldVal::String("/search%20page/"),
+        FieldVal::String("a b c"),
+        FieldVal::String(""),
+        FieldVal::String("%zz"),
+        FieldVal::Null,
+        FieldVal::String("search page"),
+        FieldVal::Null,
+        FieldVal::Null,
+        FieldVal::Null,
+    ]);
+
+    // The path keeps its escapes, while segments are decoded
+    let mut inst = UrlInst::new();
+    for field in ["path", "segments.1", "segments.2", "extension"] {
+        inst.require_field(field);
+    }
+    let vals = inst.parse(&bump, &mut FieldVal::String("/files/a%2Fb/my%20doc.txt")).unwrap();
+    assert_eq!(vals, &[
+        FieldVal::String("/files/a%2Fb/my%20doc.txt"),
+        FieldVal::String("a/b"),
+        FieldVal::String("my doc.txt"),
+        FieldVal::String("txt"),
+    ]);
+}
@@ -1,16 +1,21 @@
+use std::net::IpAddr;
+
 use indexmap::{IndexMap, IndexSet};
 use thiserror::Error;
 use time::OffsetDateTime;
 
-use crate::{ api::{self, query::QueryFilter}, parser::{ParserInst, self}, Dataset };
+use crate::{ api::{self, query::{Filter, Aggregation, SortOrder}}, parser::{ParserInst, self}, Dataset, collect::RowKey, filter::FieldFilter };
 
 #[derive(PartialEq, Clone, Copy, Debug)]
 pub(crate) enum FieldVal<'b>{
     Null,
     String(&'b str),
     Number(f64),
+    Bool(bool),
     Time(OffsetDateTime),
+    Ip(IpAddr),
     Map(&'b[(&'b str, FieldVal<'b>)]),
+    Array(&'b [FieldVal<'b>]),
 }
 
 impl<'b> std::fmt::Display for FieldVal<'b> {
@@ -19,19 +24,47 @@ impl<'b> std::fmt::Display for FieldVal<'b> {
             FieldVal::Null => Ok(()),
             FieldVal::String(s) => f.write_str(s),
             FieldVal::Number(n) => n.fmt(f),
+            FieldVal::Bool(b) => b.fmt(f),
             FieldVal::Time(t) => f.write_str(&t.format(&time::format_description::well_known::Rfc3339).unwrap()), // https://github.com/time-rs/time/issues/375
-            FieldVal::Map(_) => f.write_str(""), //TODO: serialize as JSON?
+            FieldVal::Ip(ip) => ip.fmt(f),
+            FieldVal::Map(_) | FieldVal::Array(_) => f.write_str(&serde_json::to_string(self).unwrap()),
         }
     }
 }
 
-impl<'b> FieldVal<'b> {
-    pub fn exists(&self) -> bool {
+impl<'b> serde::Serialize for FieldVal<'b> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::{SerializeMap, SerializeSeq};
         match self {
-            FieldVal::Null => false,
-            _ => true
+            FieldVal::Null => serializer.serialize_unit(),
+            FieldVal::String(s) => serializer.serialize_str(s),
+            // Integers are written without a fractional part, as by `Display`
+            FieldVal::Number(n) if n.fract() == 0.0 && n.abs() < 2f64.powi(53) => serializer.serialize_i64(*n as i64),
+            FieldVal::Number(n) => serializer.serialize_f64(*n),
+            FieldVal::Bool(b) => serializer.serialize_bool(*b),
+            FieldVal::Time(_) | FieldVal::Ip(_) => serializer.collect_str(self),
+            FieldVal::Map(pairs) => {
+                let mut map = serializer.serialize_map(Some(pairs.len()))?;
+                for (k, v) in pairs.iter() {
+                    map.serialize_entry(k, v)?;
+                }
+                map.end()
+            }
+            FieldVal::Array(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for v in items.iter() {
+                    seq.serialize_element(v)?;
+                }
+                seq.end()
+            }
         }
     }
+}
+
+impl<'b> FieldVal<'b> {
+    pub fn exists(&self) -> bool {
+        !matches!(self, FieldVal::Null)
+    }
 
     pub fn as_str(&self) -> Option<&str> {
         match self {
@@ -44,14 +77,36 @@ pub (crate) struct QueryPlan<'a> {
     pub root_fields: IndexSet<&'a str>,
     pub parsers: IndexMap<&'a str, ParserPlan<'a>>,
     pub returning: IndexMap<&'a str, FieldRef>,
-    pub filters: Vec<(FieldRef, QueryFilter)>,
+    pub filter: FilterPlan,
+    pub aggregations: IndexMap<&'a str, AggregationPlan<'a>>,
+    pub sort: Vec<(FieldRef, SortOrder)>,
+    pub limit: Option<usize>,
+    pub offset: usize,
+    pub after: Option<RowKey>,
+}
+
+pub (crate) enum FilterPlan {
+    Field(FieldRef, FieldFilter),
+    And(Vec<FilterPlan>),
+    Or(Vec<FilterPlan>),
+    Not(Box<FilterPlan>),
+}
+
+pub (crate) struct AggregationPlan<'a> {
+    pub field: Option<FieldRef>,
+    pub aggregation: &'a Aggregation,
 }
 
 pub (crate) struct ParserPlan<'a> {
     pub src: FieldRef,
     pub parser: Box<dyn ParserInst + 'a>,
+
+    /// Number of child fields required from the parser
+    pub num_fields: usize,
 }
 
+type ParserEntry<'s, 'a> = (usize, &'s mut ParserPlan<'a>);
+
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub(crate) struct FieldRef {
     pub parser: usize,
@@ -64,29 +119,52 @@ impl<'a> QueryPlan<'a> {
             root_fields: IndexSet::new(),
             parsers: IndexMap::new(),
             returning: IndexMap::new(),
-            filters: Vec::new(),
+            filter: FilterPlan::And(Vec::new()),
+            aggregations: IndexMap::new(),
+            sort: Vec::new(),
+            limit: query.limit,
+            offset: query.offset,
+            after: query.after.as_deref().map(RowKey::parse).transpose()?,
         };
 
-        for (field, filter) in query.filter.iter() {
-            let loc = plan.require_field(dataset, field)?;
-            plan.filters.push((loc, filter.clone()));
-        }
+        plan.filter = plan.plan_filter(dataset, &query.filter)?;
 
         for field in query.returning.iter() {
             let loc = plan.require_field(dataset, field)?;
             plan.returning.insert(field, loc);
         }
 
+        for (name, aggregation) in query.aggregate.iter() {
+            if let Aggregation::Histogram { interval, .. } | Aggregation::DateHistogram { interval, .. } = aggregation {
+                if !(interval.is_finite() && *interval > 0.0) {
+                    return Err(QueryError::InvalidInterval(name.to_owned()));
+                }
+            }
+
+            let field = aggregation.field().map(|field| plan.require_field(dataset, field)).transpose()?;
+            plan.aggregations.insert(name, AggregationPlan { field, aggregation });
+        }
+
+        for sort in query.sort.iter() {
+            let loc = plan.require_field(dataset, &sort.field)?;
+            plan.sort.push((loc, sort.order));
+        }
+
+        if plan.after.as_ref().is_some_and(|after| !after.matches_sort(plan.sort.len())) {
+            return Err(QueryError::InvalidCursor);
+        }
+
         Ok(plan)
     }
 
-    fn require_parser<'s>(&'s mut self, dataset: &'a Dataset, field: &'a str) -> Result<(FieldRef, Option<(usize, &'s mut ParserPlan<'a>)>) , QueryError> {
+    fn require_parser<'s>(&'s mut self, dataset: &'a Dataset, field: &'a str) -> Result<(FieldRef, Option<ParserEntry<'s, 'a>>), QueryError> {
         let src = if let Some((parent_field_name, leaf_field)) = field.rsplit_once("/") {
             let (parser_i, parser) = self.require_parser(dataset, parent_field_name)?.1
                 .ok_or_else(|| QueryError::NoParserProvides(parent_field_name.to_owned()))?;
 
             let field_index = parser.parser.require_field(leaf_field)
                 .ok_or_else(|| QueryError::FieldNoesNotExist(field.to_owned()))?;
+            parser.num_fields = parser.num_fields.max(field_index + 1);
 
             FieldRef { parser: parser_i + 1, field: field_index }
         } else {
@@ -98,7 +176,7 @@ impl<'a> QueryPlan<'a> {
             let parser_entry = self.parsers.entry(field);
             let parser_i = parser_entry.index();
             let parser = parser_entry.or_insert_with(|| ParserPlan { 
-                src, parser: parser::instance(parser_conf)
+                src, parser: parser::instance(parser_conf), num_fields: 0,
             });
             (parser_i, parser)
         });
@@ -109,6 +187,15 @@ impl<'a> QueryPlan<'a> {
     fn require_field(&mut self, dataset: &'a Dataset, field: &'a str) -> Result<FieldRef, QueryError> {
         Ok(self.require_parser(dataset, field)?.0)
     }
+
+    fn plan_filter(&mut self, dataset: &'a Dataset, filter: &'a Filter) -> Result<FilterPlan, QueryError> {
+        Ok(match filter {
+            Filter::Field(field, filter) => FilterPlan::Field(self.require_field(dataset, field)?, FieldFilter::new(filter)?),
+            Filter::And(all) => FilterPlan::And(all.iter().map(|f| self.plan_filter(dataset, f)).collect::<Result<_, _>>()?),
+            Filter::Or(any) => FilterPlan::Or(any.iter().map(|f| self.plan_filter(dataset, f)).collect::<Result<_, _>>()?),
+            Filter::Not(f) => FilterPlan::Not(Box::new(self.plan_filter(dataset, f)?)),
+        })
+    }
 }
 
 #[derive(Error, Debug)]
@@ -121,4 +208,13 @@ pub enum QueryError {
 
     #[error("Field `{0}` does not exist")]
     FieldNoesNotExist(String),
+
+    #[error("Aggregation `{0}` must have a positive interval")]
+    InvalidInterval(String),
+
+    #[error("Invalid cursor")]
+    InvalidCursor,
+
+    #[error("Invalid regex: {0}")]
+    InvalidRegex(#[from] regex::Error),
 }
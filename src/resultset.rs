@@ -1,51 +1,147 @@
 use std::{iter, slice, fmt::Display, fmt::Write};
 
-use serde::Serialize;
+use serde::{Serialize, ser::Error};
+use serde_json::value::RawValue;
+
+use crate::query::FieldVal;
+
+/// A typed cell. Text is stored in the shared buffer, starting where the
+/// previous text cell ended and ending at the contained offset.
+#[derive(Clone, Copy, Debug)]
+enum Cell {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(usize),
+    Time(usize),
+    Json(usize),
+}
+
+/// A value in a `ResultSet`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value<'a> {
+    Null,
+    String(&'a str),
+    Number(f64),
+    Bool(bool),
+
+    /// An RFC 3339 timestamp
+    Time(&'a str),
+
+    /// An object or array, as JSON text
+    Json(&'a str),
+}
 
 pub struct ResultSet {
     cols: Vec<String>,
-    ptrs: Vec<usize>,
+    cells: Vec<Cell>,
     buf: String,
 }
 
 impl ResultSet {
     pub fn new(cols: Vec<String>) -> Self {
-        Self { cols, ptrs: Vec::new(), buf: String::new() }
+        Self { cols, cells: Vec::new(), buf: String::new() }
     }
 
     pub fn push(&mut self, s: &str) {
         self.buf.push_str(s);
-        self.ptrs.push(self.buf.len())
+        self.cells.push(Cell::String(self.buf.len()))
     }
 
     pub fn push_fmt(&mut self, s: impl Display) {
         write!(self.buf,"{}", s).unwrap();
-        self.ptrs.push(self.buf.len());
+        self.cells.push(Cell::String(self.buf.len()));
+    }
+
+    pub fn push_null(&mut self) {
+        self.cells.push(Cell::Null);
+    }
+
+    pub fn push_number(&mut self, n: f64) {
+        self.cells.push(Cell::Number(n));
+    }
+
+    pub fn push_bool(&mut self, b: bool) {
+        self.cells.push(Cell::Bool(b));
+    }
+
+    pub(crate) fn push_val(&mut self, v: &FieldVal) {
+        match v {
+            FieldVal::Null => self.push_null(),
+            FieldVal::String(s) => self.push(s),
+            FieldVal::Number(n) => self.push_number(*n),
+            FieldVal::Bool(b) => self.push_bool(*b),
+            FieldVal::Ip(ip) => self.push_fmt(ip),
+            FieldVal::Time(_) => {
+                write!(self.buf, "{}", v).unwrap();
+                self.cells.push(Cell::Time(self.buf.len()));
+            }
+            FieldVal::Map(_) | FieldVal::Array(_) => {
+                write!(self.buf, "{}", v).unwrap();
+                self.cells.push(Cell::Json(self.buf.len()));
+            }
+        }
+    }
+
+    fn push_value(&mut self, v: Value) {
+        match v {
+            Value::Null => self.push_null(),
+            Value::String(s) => self.push(s),
+            Value::Number(n) => self.push_number(n),
+            Value::Bool(b) => self.push_bool(b),
+            Value::Time(s) => {
+                self.buf.push_str(s);
+                self.cells.push(Cell::Time(self.buf.len()));
+            }
+            Value::Json(s) => {
+                self.buf.push_str(s);
+                self.cells.push(Cell::Json(self.buf.len()));
+            }
+        }
     }
 
     pub fn end_row(&mut self) {
         if !self.cols.is_empty() {
-            assert_eq!(self.ptrs.len() % self.cols.len(), 0);
+            assert_eq!(self.cells.len() % self.cols.len(), 0);
         }
     }
 
     pub fn len(&self) -> usize {
-        self.ptrs.len() / self.cols.len()
+        self.cells.len() / self.cols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
     }
 
     pub fn cols(&self) -> impl Iterator<Item = &str> {
         self.cols.iter().map(|x| &x[..])
     }
 
-    pub fn rows(&self) -> ResultSetIter {
-        ResultSetIter { cols: &self.cols[..], ptrs: &self.ptrs[..], buf: &self.buf[..], pos: 0 }
+    /// Copy the rows at the specified indexes, in order, into a new `ResultSet`.
+    pub(crate) fn select(&self, rows: impl IntoIterator<Item = usize>) -> ResultSet {
+        let mut selected = ResultSet::new(self.cols.clone());
+        if self.cols.is_empty() { return selected }
+
+        let all: Vec<Row> = self.rows().collect();
+        for row in rows {
+            for v in all[row].clone() {
+                selected.push_value(v);
+            }
+            selected.end_row();
+        }
+        selected
+    }
+
+    pub fn rows(&self) -> ResultSetIter<'_> {
+        ResultSetIter { cols: &self.cols[..], cells: &self.cells[..], buf: &self.buf[..], pos: 0 }
     }
 }
 
 #[derive(Clone)]
 pub struct ResultSetIter<'a> {
     cols: &'a [String],
-    ptrs: &'a [usize],
+    cells: &'a [Cell],
     buf: &'a str,
     pos: usize,
 }
@@ -54,17 +150,17 @@ impl<'a> Iterator for ResultSetIter<'a> {
     type Item = Row<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if !self.cols.is_empty() && self.ptrs.len() >= self.cols.len() {
-            let pos = self.pos;
-            let (row, rest) = self.ptrs.split_at(self.cols.len());
-            self.pos = row.last().copied().unwrap_or(0);
-            self.ptrs = rest;
-            Some(Row {
-                pos,
-                npos: row.iter(),
-                buf: &self.buf,
+        if !self.cols.is_empty() && self.cells.len() >= self.cols.len() {
+            let (row, rest) = self.cells.split_at(self.cols.len());
+            let row = Row {
+                pos: self.pos,
+                cells: row.iter(),
+                buf: self.buf,
                 cols: self.cols,
-            })
+            };
+            self.pos = row.clone().last_text_end().unwrap_or(self.pos);
+            self.cells = rest;
+            Some(row)
         } else { None }
     }
 }
@@ -72,31 +168,46 @@ impl<'a> Iterator for ResultSetIter<'a> {
 #[derive(Clone)]
 pub struct Row<'a> {
     pos: usize,
-    npos: slice::Iter<'a, usize>,
+    cells: slice::Iter<'a, Cell>,
     buf: &'a str,
     cols: &'a [String],
 }
 
 impl<'a> Row<'a> {
-    pub fn with_col_names(self) -> impl Iterator<Item=(&'a str, &'a str)> {
+    pub fn with_col_names(self) -> impl Iterator<Item=(&'a str, Value<'a>)> {
         let cols = self.cols.iter().map(|x| &x[..]);
         iter::zip(cols, self)
     }
+
+    fn last_text_end(self) -> Option<usize> {
+        self.cells.rev().find_map(|cell| match *cell {
+            Cell::String(end) | Cell::Time(end) | Cell::Json(end) => Some(end),
+            Cell::Null | Cell::Bool(_) | Cell::Number(_) => None,
+        })
+    }
 }
 
 impl<'a> Iterator for Row<'a> {
-    type Item = &'a str;
+    type Item = Value<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.npos.next().map(|&end| {
+        let mut text = |end: usize| {
             let start = self.pos;
             self.pos = end;
             &self.buf[start..end]
+        };
+
+        self.cells.next().map(|cell| match *cell {
+            Cell::Null => Value::Null,
+            Cell::Bool(b) => Value::Bool(b),
+            Cell::Number(n) => Value::Number(n),
+            Cell::String(end) => Value::String(text(end)),
+            Cell::Time(end) => Value::Time(text(end)),
+            Cell::Json(end) => Value::Json(text(end)),
         })
     }
 }
 
-
 impl Serialize for ResultSet {
     fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error> where S: serde::Serializer {
         s.collect_seq(self.rows())
@@ -109,6 +220,18 @@ impl<'a> Serialize for Row<'a> {
     }
 }
 
+impl<'a> Serialize for Value<'a> {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error> where S: serde::Serializer {
+        match *self {
+            Value::Null => s.serialize_unit(),
+            Value::String(v) | Value::Time(v) => s.serialize_str(v),
+            Value::Number(n) => FieldVal::Number(n).serialize(s),
+            Value::Bool(b) => s.serialize_bool(b),
+            Value::Json(v) => serde_json::from_str::<&RawValue>(v).map_err(S::Error::custom)?.serialize(s),
+        }
+    }
+}
+
 #[test]
 fn test() {
     let mut rs = ResultSet::new(vec!["foo".to_owned(), "bar".to_owned()]);
@@ -119,13 +242,47 @@ fn test() {
     rs.end_row();
     assert_eq!(rs.len(), 1);
 
+    rs.push_number(200.0);
+    rs.push_null();
+    rs.end_row();
+
     rs.push("c123");
     rs.push("d456");
     rs.end_row();
-    assert_eq!(rs.len(), 2);
+    assert_eq!(rs.len(), 3);
+
+    let time = time::macros::datetime!(2022-10-01 12:00 UTC);
+    rs.push_val(&FieldVal::Map(&[("a", FieldVal::Array(&[FieldVal::Number(1.5), FieldVal::Bool(true)]))]));
+    rs.push_val(&FieldVal::Time(time));
+    rs.end_row();
+
+    rs.push_val(&FieldVal::Bool(false));
+    rs.push("");
+    rs.end_row();
+
+    use Value::*;
+    fn rows(rs: &ResultSet) -> Vec<Vec<Value<'_>>> {
+        rs.rows().map(|row| row.collect()).collect()
+    }
+    assert_eq!(rows(&rs), vec![
+        vec![String("abcdefg"), String("qw")],
+        vec![Number(200.0), Null],
+        vec![String("c123"), String("d456")],
+        vec![Json(r#"{"a":[1.5,true]}"#), Time("2022-10-01T12:00:00Z")],
+        vec![Bool(false), String("")],
+    ]);
 
-    assert_eq!(rs.rows().map(|row| row.collect::<Vec<_>>()).collect::<Vec<Vec<&str>>>(), vec![vec!["abcdefg", "qw"], vec!["c123", "d456"]]);
+    assert_eq!(serde_json::to_string(&rs).unwrap(), concat!(
+        r#"[{"foo":"abcdefg","bar":"qw"},{"foo":200,"bar":null},{"foo":"c123","bar":"d456"},"#,
+        r#"{"foo":{"a":[1.5,true]},"bar":"2022-10-01T12:00:00Z"},{"foo":false,"bar":""}]"#,
+    ));
 
-    println!("{}", serde_json::to_string(&rs).unwrap());
-    assert_eq!(serde_json::to_string(&rs).unwrap(), r#"[{"foo":"abcdefg","bar":"qw"},{"foo":"c123","bar":"d456"}]"#);
+    let sel = rs.select([2, 1, 0, 3, 2]);
+    assert_eq!(rows(&sel), vec![
+        vec![String("c123"), String("d456")],
+        vec![Number(200.0), Null],
+        vec![String("abcdefg"), String("qw")],
+        vec![Json(r#"{"a":[1.5,true]}"#), Time("2022-10-01T12:00:00Z")],
+        vec![String("c123"), String("d456")],
+    ]);
 }
@@ -8,7 +8,7 @@ use photon::{api, Dataset, Config};
 pub async fn handle_request(config: &Arc<RwLock<Config>>, request: Request<Body>) -> Result<Response<Body>, Error> {
     let is_html = request.headers().get("accept")
         .and_then(|v| v.to_str().ok())
-        .map_or(false, accepts_html);
+        .is_some_and(accepts_html);
 
     let path = request.uri().path().to_owned();
     let path_parts: Vec<_> = path.split("/").filter(|p| !p.is_empty()).collect();
@@ -31,7 +31,7 @@ pub async fn handle_request(config: &Arc<RwLock<Config>>, request: Request<Body>
         (_, &[dataset_name, ref subpath @ ..]) => {
             match config.read().await.dataset(dataset_name) {
                 Some(Ok(dataset)) => handle_dataset_request(dataset, request, subpath).await,
-                Some(Err(e)) => Err(Error::DatasetConfigError(e.to_string())),
+                Some(Err(e)) => Err(Error::DatasetConfig(e.to_string())),
                 None => Err(Error::DatasetNotFound)
             }
         }
@@ -78,8 +78,8 @@ async fn handle_dataset_request(dataset: &Dataset, mut request: Request<Body>, p
         }
         (&Method::POST, &["_query"]) => {
             let query = json_request::<api::query::Query>(&mut request).await?;
-            let results = dataset.query(&query).map_err(Error::QueryError)?; 
-            Ok(json_response(photon::api::query::Response { results }))
+            let response = dataset.query(&query).map_err(Error::Query)?;
+            Ok(json_response(response))
         }
         _ => Err(Error::InvalidRoute)
     }
@@ -110,9 +110,9 @@ fn accepts_html(v: &str) -> bool {
 
 #[test]
 fn test_accept_header() {
-    assert_eq!(accepts_html("text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,*/*;q=0.8"), true);
-    assert_eq!(accepts_html("text/html ;q=1"), true);
-    assert_eq!(accepts_html("application/json"), false)
+    assert!(accepts_html("text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,*/*;q=0.8"));
+    assert!(accepts_html("text/html ;q=1"));
+    assert!(!accepts_html("application/json"))
 }
 
 #[derive(Debug, Error)]
@@ -130,10 +130,10 @@ pub enum Error {
     InvalidRequestBody(serde_json::Error),
 
     #[error("Dataset configuration could not be loaded")]
-    DatasetConfigError(String),
+    DatasetConfig(String),
 
-    #[error("Query failed")]
-    QueryError(photon::QueryError)
+    #[error("Query failed: {0}")]
+    Query(photon::QueryError)
 }
 
 impl Error {
@@ -141,10 +141,10 @@ impl Error {
         match self {
             Error::InvalidRoute => StatusCode::NOT_FOUND,
             Error::DatasetNotFound => StatusCode::NOT_FOUND,
-            Error::DatasetConfigError(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Error::DatasetConfig(_) => StatusCode::SERVICE_UNAVAILABLE,
             Error::RequestNotJson => StatusCode::BAD_REQUEST,
             Error::InvalidRequestBody(_) => StatusCode::BAD_REQUEST,
-            Error::QueryError(_) => StatusCode::BAD_REQUEST,
+            Error::Query(_) => StatusCode::BAD_REQUEST,
             
         }
     }
@@ -155,14 +155,14 @@ impl Error {
             Error::DatasetNotFound => "dataset_not_found",
             Error::RequestNotJson => "invalid_request_json",
             Error::InvalidRequestBody(_) => "invalid_request",
-            Error::DatasetConfigError(_) => "config_error",
-            Error::QueryError(_) => "query_failed",
+            Error::DatasetConfig(_) => "config_error",
+            Error::Query(_) => "query_failed",
         }
     }
 
     fn detail(&self) -> Option<String> {
         match self {
-            Error::DatasetConfigError(e) => Some(e.to_string()),
+            Error::DatasetConfig(e) => Some(e.to_string()),
             _ => None,
         }
     }
@@ -0,0 +1,105 @@
+use std::io::{self, BufRead, Read};
+
+/// A compression format, recognized by the magic number at the start of a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Codec {
+    Gzip,
+    #[cfg(feature = "zstd")]
+    Zstd,
+    #[cfg(feature = "xz")]
+    Xz,
+    #[cfg(feature = "bzip2")]
+    Bzip2,
+    #[cfg(feature = "lz4")]
+    Lz4,
+}
+
+/// Codecs enabled by cargo features, in the order they are tried
+const CODECS: &[Codec] = &[
+    Codec::Gzip,
+    #[cfg(feature = "zstd")]
+    Codec::Zstd,
+    #[cfg(feature = "xz")]
+    Codec::Xz,
+    #[cfg(feature = "bzip2")]
+    Codec::Bzip2,
+    #[cfg(feature = "lz4")]
+    Codec::Lz4,
+];
+
+impl Codec {
+    /// Detect the codec from the first bytes of a file, returning `None` for uncompressed data.
+    pub fn detect(header: &[u8]) -> Option<Codec> {
+        CODECS.iter().copied().find(|codec| header.starts_with(codec.magic()))
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Codec::Gzip => "gzip",
+            #[cfg(feature = "zstd")]
+            Codec::Zstd => "zstd",
+            #[cfg(feature = "xz")]
+            Codec::Xz => "xz",
+            #[cfg(feature = "bzip2")]
+            Codec::Bzip2 => "bzip2",
+            #[cfg(feature = "lz4")]
+            Codec::Lz4 => "lz4",
+        }
+    }
+
+    fn magic(self) -> &'static [u8] {
+        match self {
+            Codec::Gzip => &[0x1f, 0x8b],
+            #[cfg(feature = "zstd")]
+            Codec::Zstd => &[0x28, 0xb5, 0x2f, 0xfd],
+            #[cfg(feature = "xz")]
+            Codec::Xz => &[0xfd, b'7', b'z', b'X', b'Z', 0x00],
+            #[cfg(feature = "bzip2")]
+            Codec::Bzip2 => b"BZh",
+            #[cfg(feature = "lz4")]
+            Codec::Lz4 => &[0x04, 0x22, 0x4d, 0x18],
+        }
+    }
+
+    /// Wrap a reader of compressed data in a reader of the decompressed data.
+    pub fn decoder<'a>(self, r: impl BufRead + 'a) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(match self {
+            Codec::Gzip => Box::new(flate2::bufread::GzDecoder::new(r)),
+            #[cfg(feature = "zstd")]
+            Codec::Zstd => Box::new(ruzstd::StreamingDecoder::new(r).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?),
+            #[cfg(feature = "xz")]
+            Codec::Xz => Box::new(lzma_rust2::XzReader::new(r, true)),
+            #[cfg(feature = "bzip2")]
+            Codec::Bzip2 => Box::new(bzip2::bufread::MultiBzDecoder::new(r)),
+            #[cfg(feature = "lz4")]
+            Codec::Lz4 => Box::new(lz4_flex::frame::FrameDecoder::new(r)),
+        })
+    }
+}
+
+#[test]
+fn test() {
+    let files: &[(&str, &[u8])] = &[
+        ("gzip", b"\x1f\x8b\x08\x08\xca\xaf\xd4\x6a\x00\x03\x66\x78\x2e\x74\x78\x74\x00\x4b\xcb\x2c\x2a\x2e\xe1\x2a\x4e\x4d\xce\xcf\x4b\xe1\x02\x00\x20\x51\x45\x08\x0d\x00\x00\x00"),
+        #[cfg(feature = "zstd")]
+        ("zstd", b"\x28\xb5\x2f\xfd\x24\x0d\x69\x00\x00\x66\x69\x72\x73\x74\x0a\x73\x65\x63\x6f\x6e\x64\x0a\x61\xfc\xbd\xfe"),
+        #[cfg(feature = "xz")]
+        ("xz", b"\xfd\x37\x7a\x58\x5a\x00\x00\x04\xe6\xd6\xb4\x46\x04\xc0\x11\x0d\x21\x01\x16\x00\x00\x00\x00\x00\x00\x00\x00\x00\x88\x88\xcd\x68\x01\x00\x0c\x66\x69\x72\x73\x74\x0a\x73\x65\x63\x6f\x6e\x64\x0a\x00\x00\x00\x00\x6c\x3d\xce\x7c\x87\xfd\x29\x16\x00\x01\x2d\x0d\x79\x93\x1d\x7e\x1f\xb6\xf3\x7d\x01\x00\x00\x00\x00\x04\x59\x5a"),
+        #[cfg(feature = "bzip2")]
+        ("bzip2", b"\x42\x5a\x68\x39\x31\x41\x59\x26\x53\x59\x67\x62\xd4\x8d\x00\x00\x02\xc1\x80\x00\x10\x0f\x21\x9c\x00\x20\x00\x22\x00\x69\x90\x80\x69\xa6\x89\x56\x16\x03\xc6\xd6\xf8\xbb\x92\x29\xc2\x84\x83\x3b\x16\xa4\x68"),
+        #[cfg(feature = "lz4")]
+        ("lz4", b"\x04\x22\x4d\x18\x64\x40\xa7\x0d\x00\x00\x80\x66\x69\x72\x73\x74\x0a\x73\x65\x63\x6f\x6e\x64\x0a\x00\x00\x00\x00\x07\xd6\xe8\x56"),
+    ];
+
+    for (name, data) in files {
+        let codec = Codec::detect(data).unwrap();
+        assert_eq!(codec.name(), *name);
+
+        let mut decoded = String::new();
+        codec.decoder(*data).unwrap().read_to_string(&mut decoded).unwrap();
+        assert_eq!(decoded, "first\nsecond\n", "{name}");
+    }
+
+    assert_eq!(Codec::detect(b"first\nsecond\n"), None);
+    assert_eq!(Codec::detect(b""), None);
+}
@@ -1,24 +1,219 @@
-use std::{fs::File, io::{BufRead, BufReader}};
+use std::{fs::File, io::{self, BufRead, BufReader, Read, Seek}};
 use bumpalo::Bump;
 use bumpalo::collections::String as BString;
 use bumpalo::collections::Vec as BVec;
 
-use crate::{query::{QueryPlan, QueryError, FieldVal}, ResultSet, filter::filter_test, FieldDefaults, api::fields::FieldType};
+use crate::{query::{QueryPlan, QueryError, FieldVal}, ResultSet, filter::{filter_matches, filter_partial}, FieldDefaults, api::{fields::FieldType, query::Response}, collect::Collector};
 
-use super::Source;
+use regex::bytes::Regex;
+use serde::Deserialize;
+
+use super::{Source, codec::Codec, path_template::PathTemplate};
+
+/// Joins continuation lines, such as stack traces or pretty-printed JSON, into
+/// the preceding record.
+#[derive(Clone, Deserialize)]
+#[serde(try_from = "MultilineConfig")]
+pub struct Multiline {
+    /// Lines not matching this pattern continue the previous record
+    start: Option<Regex>,
+
+    /// Lines beginning with a space or tab continue the previous record
+    indented: bool,
+
+    /// Once a record reaches either limit, the following line starts a new record
+    max_lines: usize,
+    max_bytes: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MultilineConfig {
+    start: Option<String>,
+    #[serde(default)]
+    indented: bool,
+    #[serde(default = "default_max_lines")]
+    max_lines: usize,
+    #[serde(default = "default_max_bytes")]
+    max_bytes: usize,
+}
+
+fn default_max_lines() -> usize { 500 }
+fn default_max_bytes() -> usize { 1024 * 1024 }
+
+impl TryFrom<MultilineConfig> for Multiline {
+    type Error = String;
+
+    fn try_from(config: MultilineConfig) -> Result<Self, Self::Error> {
+        if config.start.is_none() && !config.indented {
+            return Err("multiline requires `start` or `indented`".into());
+        }
+        let start = config.start.map(|s| Regex::new(&s)).transpose().map_err(|e| e.to_string())?;
+        Ok(Multiline { start, indented: config.indented, max_lines: config.max_lines, max_bytes: config.max_bytes })
+    }
+}
+
+impl Multiline {
+    fn is_continuation(&self, line: &[u8]) -> bool {
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        (self.indented && matches!(line.first(), Some(b' ' | b'\t')))
+            || self.start.as_ref().is_some_and(|re| !re.is_match(line))
+    }
+}
+
+/// Reads records of one or more lines, including their trailing newlines.
+pub(super) struct Records<'a, R> {
+    file: R,
+    multiline: Option<&'a Multiline>,
+
+    /// Line read past the end of the previous record
+    next: Vec<u8>,
+}
+
+impl<'a, R: BufRead> Records<'a, R> {
+    pub(super) fn new(file: R, multiline: Option<&'a Multiline>) -> Self {
+        Records { file, multiline, next: Vec::new() }
+    }
+
+    /// Read the next record into `buf`, returning its size, or 0 at the end of the file.
+    fn read(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        buf.clear();
+        if self.next.is_empty() {
+            self.file.read_until(b'\n', buf)?;
+        } else {
+            std::mem::swap(buf, &mut self.next);
+        }
+
+        let Some(multiline) = self.multiline else { return Ok(buf.len()) };
+        let mut lines = 1;
+
+        while !buf.is_empty() && lines < multiline.max_lines && buf.len() < multiline.max_bytes {
+            self.next.clear();
+            if self.file.read_until(b'\n', &mut self.next)? == 0 || !multiline.is_continuation(&self.next) {
+                break;
+            }
+            buf.append(&mut self.next);
+            lines += 1;
+        }
+
+        Ok(buf.len())
+    }
+}
+
+fn is_zip(header: &[u8]) -> bool {
+    header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06")
+}
+
+fn is_tar(header: &[u8]) -> bool {
+    header.get(257..262) == Some(b"ustar")
+}
 
 pub(crate) struct FileLines {
     glob_pattern: glob::Pattern,
+    multiline: Option<Multiline>,
+
+    /// Archive members to read, matched against their path within the archive
+    members: Option<glob::Pattern>,
+
+    /// Extracts fields from the path of each file
+    path_template: Option<PathTemplate>,
 }
 
 impl FileLines {
-    pub(crate) fn new(path_glob: &str) -> Result<FileLines, &'static str> {
-        Ok(Self { glob_pattern: glob::Pattern::new(path_glob).map_err(|x| x.msg)? })
+    pub(crate) fn new(path_glob: &str, multiline: Option<Multiline>, members: Option<&str>, path_template: Option<PathTemplate>) -> Result<FileLines, &'static str> {
+        Ok(Self {
+            glob_pattern: glob::Pattern::new(path_glob).map_err(|x| x.msg)?,
+            multiline,
+            members: members.map(|m| glob::Pattern::new(m.trim_start_matches('/'))).transpose().map_err(|x| x.msg)?,
+            path_template,
+        })
+    }
+
+    /// Name of an archive member, such as `logs.tar.gz!/var/log/app.log`, if it should be read
+    fn member_name(&self, archive: &str, path: &str) -> Option<String> {
+        let path = path.trim_start_matches('/');
+        let options = glob::MatchOptions { require_literal_separator: true, ..Default::default() };
+        if self.members.as_ref().is_some_and(|m| !m.matches_with(path, options)) {
+            return None;
+        }
+        Some(format!("{archive}!/{path}"))
+    }
+
+    /// Read a possibly-decompressed stream, which is either lines or a tar archive.
+    fn read_stream(&self, fname: &str, path_fields: &[(&str, &str)], mut reader: impl BufRead, plan: &QueryPlan, collector: &mut Collector) -> Result<(), QueryError> {
+        if is_tar(reader.fill_buf()?) {
+            self.read_tar(fname, path_fields, reader, plan, collector)
+        } else {
+            read_lines(fname, path_fields, Records::new(reader, self.multiline.as_ref()), plan, collector)
+        }
+    }
+
+    fn read_tar(&self, fname: &str, path_fields: &[(&str, &str)], reader: impl Read, plan: &QueryPlan, collector: &mut Collector) -> Result<(), QueryError> {
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries()? {
+            if collector.is_done() { break; }
+
+            let entry = entry?;
+            if !entry.header().entry_type().is_file() { continue; }
+
+            // A member whose path can't be decoded doesn't fail the whole query
+            let Ok(path) = entry.path() else {
+                collector.stats.files_skipped += 1;
+                continue;
+            };
+            let Some(name) = self.member_name(fname, &path.to_string_lossy()) else { continue };
+            self.read_member(&name, path_fields, BufReader::new(entry), plan, collector)?;
+        }
+        Ok(())
+    }
+
+    fn read_zip(&self, fname: &str, path_fields: &[(&str, &str)], reader: impl Read + Seek, plan: &QueryPlan, collector: &mut Collector) -> Result<(), QueryError> {
+        let mut archive = zip::ZipArchive::new(reader).map_err(io::Error::from)?;
+        for i in 0..archive.len() {
+            if collector.is_done() { break; }
+
+            let name = {
+                let entry = archive.by_index_raw(i).map_err(io::Error::from)?;
+                if !entry.is_file() { continue; }
+                let Some(name) = self.member_name(fname, entry.name()) else { continue };
+                name
+            };
+
+            let entry = match archive.by_index(i) {
+                Ok(entry) => entry,
+                // A member compressed with a method that isn't enabled doesn't fail the whole query
+                Err(zip::result::ZipError::UnsupportedArchive(_)) => {
+                    collector.stats.files_skipped += 1;
+                    continue;
+                }
+                Err(e) => return Err(io::Error::from(e).into()),
+            };
+            self.read_member(&name, path_fields, BufReader::new(entry), plan, collector)?;
+        }
+        Ok(())
+    }
+
+    /// Read an archive member as if it were a file, except that archives are not nested.
+    fn read_member(&self, name: &str, path_fields: &[(&str, &str)], mut reader: impl BufRead, plan: &QueryPlan, collector: &mut Collector) -> Result<(), QueryError> {
+        if !collector.wants_file(name) {
+            collector.stats.files_skipped += 1;
+            return Ok(());
+        }
+        collector.stats.files_opened += 1;
+
+        if let Some(codec) = Codec::detect(reader.fill_buf()?) {
+            *collector.stats.codecs.entry(codec.name().to_owned()).or_default() += 1;
+            let reader = BufReader::new(codec.decoder(reader)?);
+            read_lines(name, path_fields, Records::new(reader, self.multiline.as_ref()), plan, collector)
+        } else {
+            read_lines(name, path_fields, Records::new(reader, self.multiline.as_ref()), plan, collector)
+        }
     }
 }
 
 impl Source for FileLines {
-    fn query(&self, plan: QueryPlan) -> Result<ResultSet, QueryError> {
+    fn query(&self, plan: QueryPlan) -> Result<Response<ResultSet>, QueryError> {
         let mut files = glob::glob(self.glob_pattern.as_str())
             .unwrap() // Pattern is already checked, but `glob` provides no API to avoid re-parsing the `Pattern`
             .filter_map(Result::ok)
@@ -28,46 +223,100 @@ impl Source for FileLines {
             natord::compare(&a.to_string_lossy(), &b.to_string_lossy()).reverse()
         );
        
-        let mut rows_scanned = 0;
-        let mut results = ResultSet::new(plan.returning.keys().map(|n| n.to_string()).collect());
+        let mut collector = Collector::new(&plan);
 
         for fname in files {
-            let mut file = BufReader::new(File::open(&fname)?);
+            if collector.is_done() { break; }
+
             let fname_str = fname.to_string_lossy();
+            let path_fields = self.path_template.as_ref().map_or(Vec::new(), |t| t.fields(&fname_str));
+            if !collector.wants_file(&fname_str) || !path_may_match(&plan, &path_fields) {
+                collector.stats.files_skipped += 1;
+                continue;
+            }
 
-            let b = file.fill_buf()?;
-            if b.starts_with(&[0x1f, 0x8b]) {
-                let reader = BufReader::new(flate2::bufread::GzDecoder::new(file));
-                read_lines(&fname_str, reader, &plan, &mut results, &mut rows_scanned)?;
+            let mut file = BufReader::new(File::open(&fname)?);
+            collector.stats.files_opened += 1;
+
+            let header = file.fill_buf()?;
+            if is_zip(header) {
+                self.read_zip(&fname_str, &path_fields, file, &plan, &mut collector)?;
+            } else if let Some(codec) = Codec::detect(header) {
+                *collector.stats.codecs.entry(codec.name().to_owned()).or_default() += 1;
+                let reader = BufReader::new(codec.decoder(file)?);
+                self.read_stream(&fname_str, &path_fields, reader, &plan, &mut collector)?;
             } else {
-                read_lines(&fname_str, file, &plan, &mut results, &mut rows_scanned)?;
+                self.read_stream(&fname_str, &path_fields, file, &plan, &mut collector)?;
             }
         }
-        
-       Ok(results)
+
+        Ok(collector.finish())
     }
 
     fn fields(&self) -> Vec<(&str, FieldDefaults)> {
-        vec![
+        let mut fields = vec![
             ("filename", FieldDefaults { ty: FieldType::Keyword }),
             ("line",     FieldDefaults { ty: FieldType::Number }),
             ("offset",   FieldDefaults { ty: FieldType::Number }),
-        ]
+        ];
+        for name in self.path_template.iter().flat_map(PathTemplate::names) {
+            fields.push((name, FieldDefaults { ty: FieldType::Keyword }));
+        }
+        fields
+    }
+}
+
+/// Whether a file with these path fields could contain matching records,
+/// evaluating the parsers and filters that depend only on the path fields.
+fn path_may_match(plan: &QueryPlan, path_fields: &[(&str, &str)]) -> bool {
+    if path_fields.is_empty() { return true; }
+
+    let bump = Bump::new();
+    let path_field = |field: &str| path_fields.iter().find(|(k, _)| *k == field).map(|&(_, v)| v);
+
+    let mut root_data: Vec<FieldVal> = plan.root_fields.iter()
+        .map(|&field| path_field(field).map_or(FieldVal::Null, FieldVal::String))
+        .collect();
+    let mut known = vec![plan.root_fields.iter().map(|&field| path_field(field).is_some()).collect::<Vec<_>>()];
+
+    let mut data = BVec::new_in(&bump);
+    data.push(&mut root_data[..]);
+
+    for parser in plan.parsers.values() {
+        let is_known = known[parser.src.parser][parser.src.field];
+        let input = &mut data[parser.src.parser][parser.src.field];
+        let vals = if is_known && input.exists() { parser.parser.parse(&bump, input) } else { None };
+        data.push(vals.unwrap_or_else(|| bump.alloc_slice_fill_copy(parser.num_fields, FieldVal::Null)));
+        known.push(vec![is_known; parser.num_fields]);
     }
+
+    filter_partial(&plan.filter, &data, &|loc| known[loc.parser][loc.field]) != Some(false)
 }
 
-fn read_lines(fname: &str, mut file: impl BufRead, plan: &QueryPlan, results: &mut ResultSet, rows_scanned: &mut i32) -> Result<(), QueryError> {
+pub(super) fn read_lines(fname: &str, path_fields: &[(&str, &str)], mut records: Records<impl BufRead>, plan: &QueryPlan, collector: &mut Collector) -> Result<(), QueryError> {
     let mut bump = Bump::new();
     bump.set_allocation_limit(Some(16 * 1024 * 1024));
     let mut buf = Vec::new();
     let mut pos = 0;
-    'line: loop {
-        *rows_scanned += 1;
-    
+    loop {
+        if collector.is_done() { break; }
+
         buf.clear();
-        let read_size = file.read_until(b'\n', &mut buf)?;
+        let read_size = records.read(&mut buf)?;
         if read_size == 0 { break; }
 
+        collector.stats.rows_scanned += 1;
+        collector.stats.bytes_read += read_size as u64;
+
+        let offset = pos;
+        pos += read_size;
+
+        // Parsers see the first record of each file even if it is otherwise
+        // skipped, because it may be a header needed to parse the rest.
+        let start_of_file = offset == 0;
+        let wanted = collector.wants_record(fname, offset as u64);
+        if !wanted && !start_of_file { continue; }
+
         bump.reset();
     
         let mut root_data = BVec::new_in(&bump);
@@ -77,11 +326,11 @@ fn read_lines(fname: &str, mut file: impl BufRead, plan: &QueryPlan, results: &m
                 "filename" => FieldVal::String(fname),
                 "line" => FieldVal::String(
                     std::str::from_utf8(&buf).unwrap_or_else(|_|{
-                        BString::from_utf8_lossy_in(&*buf, &bump).into_bump_str()
+                        BString::from_utf8_lossy_in(&buf, &bump).into_bump_str()
                     }).trim_end_matches('\n')
                 ),
-                "offset" => FieldVal::Number(pos as f64),
-                _ => FieldVal::Null,
+                "offset" => FieldVal::Number(offset as f64),
+                _ => path_fields.iter().find(|(k, _)| *k == field).map_or(FieldVal::Null, |&(_, v)| FieldVal::String(v)),
             };
     
             root_data.push(v);
@@ -90,22 +339,230 @@ fn read_lines(fname: &str, mut file: impl BufRead, plan: &QueryPlan, results: &m
         let mut data = BVec::new_in(&bump);
         data.push(&mut root_data[..]);
     
-        for parser in plan.parsers.values() {
-            let vals = parser.parser.parse(&bump, &mut data[parser.src.parser][parser.src.field]);
+        let mut is_header = false;
+        for (i, parser) in plan.parsers.values().enumerate() {
+            let input = &mut data[parser.src.parser][parser.src.field];
+            if start_of_file && parser.parser.file_header(&bump, input) {
+                is_header = true;
+                break;
+            }
+
+            let vals = if input.exists() {
+                parser.parser.parse(&bump, input)
+            } else { None };
+
+            let vals = vals.unwrap_or_else(|| {
+                if input.exists() { collector.parse_failed(i); }
+                bump.alloc_slice_fill_copy(parser.num_fields, FieldVal::Null)
+            });
             data.push(vals);
         }
-    
-        for (loc, filter) in &plan.filters {
-            if !filter_test(filter, &data[loc.parser][loc.field]) {
-                continue 'line;
-            }
+
+        if is_header || !wanted {
+            continue;
         }
     
-        for loc in plan.returning.values() {
-            results.push_fmt(&data[loc.parser][loc.field]);
+        if !filter_matches(&plan.filter, &data) {
+            continue;
         }
-        results.end_row();
-        pos += read_size;
+    
+        collector.push(fname, offset as u64, &data);
     }
     Ok(())
 }
+
+#[test]
+fn test_multiline() {
+    let input = concat!(
+        "2022-10-01 ERROR boom\n",
+        "java.lang.RuntimeException: boom\n",
+        "\tat Foo.bar(Foo.java:10)\n",
+        "\tat Foo.main(Foo.java:3)\n",
+        "2022-10-01 INFO ok\n",
+        "  indented\n",
+        "2022-10-01 INFO last",
+    );
+
+    let read_all = |multiline: Option<&Multiline>| {
+        let mut records = Records::new(input.as_bytes(), multiline);
+        let mut buf = Vec::new();
+        let mut all = Vec::new();
+        while records.read(&mut buf).unwrap() > 0 {
+            all.push(String::from_utf8(buf.clone()).unwrap());
+        }
+        assert_eq!(all.concat(), input);
+        all
+    };
+    let multiline = |toml_src: &str| toml::from_str::<Multiline>(toml_src).unwrap();
+
+    assert_eq!(read_all(None).len(), 7);
+
+    let start = multiline(r#"start = '^\d{4}-\d{2}-\d{2} '"#);
+    assert_eq!(read_all(Some(&start)), [
+        "2022-10-01 ERROR boom\njava.lang.RuntimeException: boom\n\tat Foo.bar(Foo.java:10)\n\tat Foo.main(Foo.java:3)\n",
+        "2022-10-01 INFO ok\n  indented\n",
+        "2022-10-01 INFO last",
+    ]);
+
+    let indented = multiline("indented = true");
+    assert_eq!(read_all(Some(&indented)), [
+        "2022-10-01 ERROR boom\n",
+        "java.lang.RuntimeException: boom\n\tat Foo.bar(Foo.java:10)\n\tat Foo.main(Foo.java:3)\n",
+        "2022-10-01 INFO ok\n  indented\n",
+        "2022-10-01 INFO last",
+    ]);
+
+    let limited = multiline(r#"start = '^\d'
+max_lines = 3"#);
+    assert_eq!(read_all(Some(&limited))[..2], [
+        "2022-10-01 ERROR boom\njava.lang.RuntimeException: boom\n\tat Foo.bar(Foo.java:10)\n",
+        "\tat Foo.main(Foo.java:3)\n",
+    ]);
+
+    assert!(toml::from_str::<Multiline>("max_lines = 10").is_err());
+    assert!(toml::from_str::<Multiline>("start = '('").is_err());
+}
+
+#[test]
+fn test_archive_members() {
+    let mut tar = tar::Builder::new(Vec::new());
+    let mut header = tar::Header::new_ustar();
+    header.set_size(4);
+    tar.append_data(&mut header, "var/log/app.log", &b"one\n"[..]).unwrap();
+    let tar = tar.into_inner().unwrap();
+    assert!(is_tar(&tar) && !is_zip(&tar));
+
+    let mut zip = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+    zip.start_file("app.log", zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored)).unwrap();
+    io::Write::write_all(&mut zip, b"one\n").unwrap();
+    let zip = zip.finish().unwrap().into_inner();
+    assert!(is_zip(&zip) && !is_tar(&zip));
+    assert!(!is_tar(b"one\n") && !is_zip(b"one\n"));
+
+    let all = FileLines::new("*.tar", None, None, None).unwrap();
+    assert_eq!(all.member_name("a.tar", "/etc/hosts").as_deref(), Some("a.tar!/etc/hosts"));
+
+    let logs = FileLines::new("*.tar", None, Some("/var/log/*.log"), None).unwrap();
+    assert_eq!(logs.member_name("a.tar", "var/log/app.log").as_deref(), Some("a.tar!/var/log/app.log"));
+    assert_eq!(logs.member_name("a.tar", "var/log/old/app.log"), None);
+    assert_eq!(logs.member_name("a.tar", "etc/hosts"), None);
+}
+
+/// Run a query against a dataset reading these files from a temporary directory,
+/// with `config` appended to its configuration.
+#[cfg(test)]
+fn query_files(files: &[(&str, &[u8])], config: &str, query: &str) -> Response<ResultSet> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static DIRS: AtomicUsize = AtomicUsize::new(0);
+
+    let dir = std::env::temp_dir().join(format!("photon-test-{}-{}", std::process::id(), DIRS.fetch_add(1, Ordering::Relaxed)));
+    std::fs::create_dir_all(&dir).unwrap();
+    for (name, data) in files {
+        std::fs::write(dir.join(name), data).unwrap();
+    }
+
+    // `$DIR` in the config stands for the temporary directory
+    let config = config.replace("$DIR", &dir.to_string_lossy());
+    let conf = toml::from_str(&format!("[source]\nsource = \"file_lines\"\npath = \"{}/*\"\n{config}", dir.display())).unwrap();
+    let response = crate::Dataset::from_config(&conf).unwrap().query(&serde_json::from_str(query).unwrap());
+    std::fs::remove_dir_all(&dir).unwrap();
+    response.unwrap()
+}
+
+#[test]
+fn test_archive_unsupported_member() {
+    let mut zip = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+    for name in ["a.log", "b.log"] {
+        zip.start_file(name, zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored)).unwrap();
+        io::Write::write_all(&mut zip, b"one\ntwo\n").unwrap();
+    }
+    let mut zip = zip.finish().unwrap().into_inner();
+
+    // Mark `b.log` as compressed with bzip2, which this build of `zip` doesn't support,
+    // in both its local header and its central directory entry
+    let local = zip.windows(4).rposition(|w| w == b"PK\x03\x04").unwrap();
+    zip[local + 8] = 12;
+    let central = zip.windows(4).rposition(|w| w == b"PK\x01\x02").unwrap();
+    zip[central + 10] = 12;
+
+    let res = query_files(&[("a.zip", &zip), ("c.log", b"three\n")], "", r#"{"returning": ["filename", "line"]}"#);
+    let rows: Vec<_> = res.results.rows().map(|row| match row.collect::<Vec<_>>()[..] {
+        // Strip the temporary directory from the file name
+        [crate::Value::String(file), crate::Value::String(line)] => format!("{}:{line}", file.split_once("/photon-test-").unwrap().1.split_once('/').unwrap().1),
+        ref row => panic!("{row:?}"),
+    }).collect();
+    assert_eq!(rows, ["c.log:three", "a.zip!/a.log:one", "a.zip!/a.log:two"]);
+    assert_eq!((res.stats.files_opened, res.stats.files_skipped), (3, 1));
+}
+
+#[test]
+fn test_stats() {
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    io::Write::write_all(&mut gz, b"3\n4\n").unwrap();
+    let gz = gz.finish().unwrap();
+
+    let files: &[(&str, &[u8])] = &[("a.log", b"1\n2\nx\n"), ("b.log.gz", &gz)];
+    let config = "[fields.line]\nparser = \"number\"";
+
+    let res = query_files(files, config, r#"{"filter": {"line": {"min": 2}}, "returning": ["line"]}"#);
+    assert_eq!(res.results.len(), 3);
+    assert_eq!(res.stats.rows_scanned, 5);
+    assert_eq!(res.stats.rows_matched, 3);
+    assert_eq!(res.stats.bytes_read, 10);
+    assert_eq!((res.stats.files_opened, res.stats.files_skipped), (2, 0));
+    assert_eq!(res.stats.codecs.into_iter().collect::<Vec<_>>(), [("gzip".to_owned(), 1)]);
+    assert_eq!(res.stats.parse_failures.into_iter().collect::<Vec<_>>(), [("line".to_owned(), 1)]);
+
+    // The scan stops at the first row past the limit, without counting later files as skipped
+    let res = query_files(files, config, r#"{"returning": ["line"], "limit": 1}"#);
+    assert_eq!(res.stats.rows_scanned, 2);
+    assert_eq!(res.stats.rows_matched, 2);
+    assert_eq!((res.stats.files_opened, res.stats.files_skipped), (1, 0));
+    assert!(res.next.is_some());
+}
+
+#[test]
+fn test_path_template_skips_files() {
+    let files: &[(&str, &[u8])] = &[("api-web1.log", b"a\n"), ("api-web2.log", b"b\n"), ("db-web1.log", b"c\n"), ("cron-web1.log", b"d\n")];
+    let config = "path_template = \"$DIR/{service}-{host}.log\"";
+    let query = |filter: &str| {
+        let res = query_files(files, config, &format!(r#"{{"filter": {filter}, "returning": ["service", "host", "line"]}}"#));
+        let rows: Vec<_> = res.results.rows().map(|row| match row.collect::<Vec<_>>()[..] {
+            [crate::Value::String(service), crate::Value::String(host), crate::Value::String(line)] => format!("{service}/{host}:{line}"),
+            ref row => panic!("{row:?}"),
+        }).collect();
+        (rows, res.stats.files_opened, res.stats.files_skipped)
+    };
+
+    assert_eq!(query(r#"{"service": {"is": ["api"]}}"#), (vec!["api/web2:b".to_owned(), "api/web1:a".to_owned()], 2, 2));
+    assert_eq!(query(r#"{"service": {"is": ["api"]}, "not": {"host": {"is": ["web2"]}}}"#), (vec!["api/web1:a".to_owned()], 1, 3));
+    assert_eq!(query(r#"{"not": {"service": {"is": ["api", "db"]}}}"#), (vec!["cron/web1:d".to_owned()], 1, 3));
+
+    // Files are only skipped when the path fields alone rule them out
+    assert_eq!(query(r#"{"or": [{"service": {"is": ["db"]}}, {"line": {"is": ["a"]}}]}"#), (vec!["db/web1:c".to_owned(), "api/web1:a".to_owned()], 4, 0));
+}
+
+#[test]
+fn test_archive_tar_gz() {
+    let mut tar = tar::Builder::new(Vec::new());
+    for (path, data) in [("var/log/app.log", &b"one\n"[..]), ("var/log/old/app.log", b"two\n"), ("etc/hosts", b"three\n")] {
+        let mut header = tar::Header::new_ustar();
+        header.set_size(data.len() as u64);
+        tar.append_data(&mut header, path, data).unwrap();
+    }
+    let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    io::Write::write_all(&mut gz, &tar.into_inner().unwrap()).unwrap();
+    let gz = gz.finish().unwrap();
+
+    let query = |config: &str| {
+        let res = query_files(&[("archive.tar.gz", &gz)], config, r#"{"returning": ["filename", "line"]}"#);
+        let rows: Vec<_> = res.results.rows().map(|row| match row.collect::<Vec<_>>()[..] {
+            [crate::Value::String(file), crate::Value::String(line)] => format!("{}:{line}", file.split_once("/photon-test-").unwrap().1.split_once('/').unwrap().1),
+            ref row => panic!("{row:?}"),
+        }).collect();
+        (rows, res.stats.files_opened, res.stats.files_skipped)
+    };
+
+    assert_eq!(query(""), (vec!["archive.tar.gz!/var/log/app.log:one".to_owned(), "archive.tar.gz!/var/log/old/app.log:two".to_owned(), "archive.tar.gz!/etc/hosts:three".to_owned()], 4, 0));
+    assert_eq!(query("members = \"/var/log/*.log\""), (vec!["archive.tar.gz!/var/log/app.log:one".to_owned()], 2, 0));
+}
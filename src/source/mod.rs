@@ -1,16 +1,20 @@
-mod file;
+pub mod file;
+pub mod path_template;
+mod stdin;
+mod codec;
 
-use crate::{query::{QueryPlan, QueryError}, ResultSet, ConfigError, FieldDefaults};
+use crate::{query::{QueryPlan, QueryError}, ResultSet, ConfigError, FieldDefaults, api::query::Response};
 
 pub(crate) trait Source: Send + Sync {
-    fn query(&self, plan: QueryPlan) -> Result<ResultSet, QueryError>;
+    fn query(&self, plan: QueryPlan) -> Result<Response<ResultSet>, QueryError>;
 
     fn fields(&self) -> Vec<(&str, FieldDefaults)>;
 }
 
-pub(crate) fn new(spec: &crate::config::dataset::SourceKind) -> Result<Box<impl Source>, ConfigError> {
+pub(crate) fn new(spec: &crate::config::dataset::SourceKind) -> Result<Box<dyn Source>, ConfigError> {
     use crate::config::dataset::SourceKind::*;
     Ok(match spec {
-        FileLines { path } => Box::new(file::FileLines::new(path).map_err(ConfigError::InvalidConfig)?),
+        FileLines { path, multiline, members, path_template } => Box::new(file::FileLines::new(path, multiline.clone(), members.as_deref(), path_template.clone()).map_err(ConfigError::InvalidConfig)?),
+        Stdin { multiline } => Box::new(stdin::Stdin::new(multiline.clone())),
     })
 }
@@ -0,0 +1,75 @@
+use regex::Regex;
+use serde::Deserialize;
+
+/// Extracts fields from file paths, like `/logs/{service}/{date}/{host}.log`.
+/// Each `{name}` matches one non-empty path segment or part of a segment.
+#[derive(Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct PathTemplate {
+    regex: Regex,
+    names: Vec<String>,
+}
+
+impl TryFrom<String> for PathTemplate {
+    type Error = String;
+
+    fn try_from(template: String) -> Result<Self, Self::Error> {
+        let mut pattern = String::from("^");
+        let mut names: Vec<String> = Vec::new();
+        let mut rest = &template[..];
+
+        while let Some(start) = rest.find('{') {
+            pattern.push_str(&regex::escape(&rest[..start]));
+            let end = rest[start..].find('}').ok_or("unclosed `{` in path template")? + start;
+            let name = &rest[start + 1..end];
+
+            if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err(format!("invalid field name `{name}` in path template"));
+            }
+            if matches!(name, "filename" | "line" | "offset") || names.iter().any(|n| n == name) {
+                return Err(format!("duplicate field `{name}` in path template"));
+            }
+
+            pattern.push_str("([^/]+)");
+            names.push(name.to_owned());
+            rest = &rest[end + 1..];
+        }
+        pattern.push_str(&regex::escape(rest));
+        pattern.push('$');
+
+        Ok(PathTemplate { regex: Regex::new(&pattern).map_err(|e| e.to_string())?, names })
+    }
+}
+
+impl PathTemplate {
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(|n| &n[..])
+    }
+
+    /// Field values extracted from `path`, or none if it doesn't match the template.
+    pub fn fields<'a>(&'a self, path: &'a str) -> Vec<(&'a str, &'a str)> {
+        let Some(captures) = self.regex.captures(path) else { return Vec::new() };
+        self.names().zip(captures.iter().skip(1))
+            .filter_map(|(name, m)| Some((name, m?.as_str())))
+            .collect()
+    }
+}
+
+#[test]
+fn test() {
+    let template = |s: &str| PathTemplate::try_from(s.to_owned());
+    let t = template("/logs/{service}/{date}/{host}.log").unwrap();
+
+    assert_eq!(t.names().collect::<Vec<_>>(), ["service", "date", "host"]);
+    assert_eq!(t.fields("/logs/api/2022-10-01/web-1.log"), [("service", "api"), ("date", "2022-10-01"), ("host", "web-1")]);
+    assert_eq!(t.fields("/logs/api/2022-10-01/web.1.log"), [("service", "api"), ("date", "2022-10-01"), ("host", "web.1")]);
+    assert_eq!(t.fields("/logs/api/2022/10/web-1.log"), []);
+    assert_eq!(t.fields("/logs/api/2022-10-01/web-1.log.gz"), []);
+
+    assert_eq!(template("a+b/{x}").unwrap().fields("a+b/1"), [("x", "1")]);
+    assert!(template("/logs/{service").is_err());
+    assert!(template("/logs/{}").is_err());
+    assert!(template("/logs/{a b}").is_err());
+    assert!(template("/logs/{line}").is_err());
+    assert!(template("/{x}/{x}").is_err());
+}
@@ -0,0 +1,34 @@
+use std::io;
+
+use crate::{query::{QueryPlan, QueryError}, ResultSet, FieldDefaults, api::{fields::FieldType, query::Response}, collect::Collector};
+
+use super::{Source, file::{Multiline, Records, read_lines}};
+
+/// Reads lines piped to the process, with `-` as their `filename`. Standard input can only be consumed
+/// once, so later queries see no records.
+pub(crate) struct Stdin {
+    multiline: Option<Multiline>,
+}
+
+impl Stdin {
+    pub(crate) fn new(multiline: Option<Multiline>) -> Stdin {
+        Stdin { multiline }
+    }
+}
+
+impl Source for Stdin {
+    fn query(&self, plan: QueryPlan) -> Result<Response<ResultSet>, QueryError> {
+        let mut collector = Collector::new(&plan);
+        let stdin = io::stdin().lock();
+        read_lines("-", &[], Records::new(stdin, self.multiline.as_ref()), &plan, &mut collector)?;
+        Ok(collector.finish())
+    }
+
+    fn fields(&self) -> Vec<(&str, FieldDefaults)> {
+        vec![
+            ("filename", FieldDefaults { ty: FieldType::Keyword }),
+            ("line",     FieldDefaults { ty: FieldType::Number }),
+            ("offset",   FieldDefaults { ty: FieldType::Number }),
+        ]
+    }
+}
@@ -0,0 +1,48 @@
+use std::{fs, io::Write, process::{Command, Output, Stdio}};
+
+fn query_stdin(config_dir: &std::path::Path, dataset: &str, query: &str, input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_photon"))
+        .args(["query", "--stdin", "-d", dataset, "-q", query, "-c"])
+        .arg(config_dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_query_stdin() {
+    let dir = std::env::temp_dir().join(format!("photon-test-stdin-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("app.dataset.toml"), r#"
+        [source]
+        source = "file_lines"
+        path = "/nonexistent/*.log"
+        path_template = "/nonexistent/{service}.log"
+        multiline = { indented = true }
+
+        [fields.line]
+        parser = "json"
+    "#).unwrap();
+
+    let input = "{\"level\": \"info\"}\n{\"level\": \"error\",\n  \"msg\": \"boom\"}\n";
+    let out = query_stdin(&dir, "app", r#"{"filter": {"line/level": {"is": ["error"]}}, "returning": ["filename", "offset", "line/msg", "service"]}"#, input);
+    assert!(out.status.success());
+    let response: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(response["results"], serde_json::json!([{"filename": "-", "offset": 18, "line/msg": "boom", "service": null}]));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("null when reading standard input: service"));
+
+    let out = query_stdin(&dir, "missing", "{}", "");
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("Dataset `missing` does not exist"));
+
+    fs::write(dir.join("bad.dataset.toml"), "[source]\nsource = \"nope\"").unwrap();
+    let out = query_stdin(&dir, "bad", "{}", "");
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("Configuration error for dataset `bad`"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -14,7 +14,8 @@ export function DatasetView({ datasetName, onChangeDataset }: DatasetViewProps)
 
     const data = useReq<QueryReq, QueryRes>('post', `/${datasetName}/_query`, {
         filter: state.filter,
-        returning: state.fields
+        returning: state.fields,
+        limit: 1000,
     });
 
     let body = (() => {
@@ -24,7 +25,7 @@ export function DatasetView({ datasetName, onChangeDataset }: DatasetViewProps)
                     <Sidebar fields={fields.data} state={state} dispatch={dispatch} />
                 </div>
                 <div id='data'>
-                    <Table dataRes={data} state={state} dispatch={dispatch} />
+                    <Table fields={fields.data} dataRes={data} state={state} dispatch={dispatch} />
                 </div>
             </>);
         } else if (fields.status == 'err') {
@@ -33,6 +33,10 @@ export function Sidebar({fields, state, dispatch}: SidebarProps) {
             searchAction = { 'type': 'filterKeywordSet', 'field': searchField, 'values': searchArg.split(','), include: false };
         } else if (searchOp == '!*') {
             searchAction = { 'type': 'filterPresent', 'field': searchField, present: false };
+        } else if (searchOp == '~' && searchArg) {
+            searchAction = { 'type': 'filter', 'field': searchField, filter: { contains: searchArg, case_insensitive: true } };
+        } else if (searchOp == '@' && searchArg) {
+            searchAction = { 'type': 'filter', 'field': searchField, filter: { cidr: searchArg.split(',') } };
         } else if (searchOp == "#") {
             searchAction = { 'type': 'filterClear', field: searchField }
         }
@@ -119,6 +123,8 @@ function Field({ fieldName, field, selectField, selected, state, dispatch }: Fie
 
             {selected && field.type == 'timestamp' &&
                 <FilterEditTime fieldName={fieldName} field={field} filter={state.filter[fieldName]} dispatch={dispatch} /> }
+            {selected && field.type == 'boolean' &&
+                <FilterEditBoolean fieldName={fieldName} field={field} filter={state.filter[fieldName]} dispatch={dispatch} /> }
             {selected && field.type == 'keyword' &&
                 <FilterEditKeyword fieldName={fieldName} field={field} filter={state.filter[fieldName]} dispatch={dispatch} />}
         </div>
@@ -146,6 +152,48 @@ function FilterList({filter}: FilterProps) {
                 not: {filter.not.map((v) => (<li>{v}</li>))}
             </ul>
         );
+    } else if (filter && "contains" in filter) {
+        return (
+            <ul class='filter filter-text'>
+                contains: <li>{filter.contains}</li>
+            </ul>
+        );
+    } else if (filter && "prefix" in filter) {
+        return (
+            <ul class='filter filter-text'>
+                prefix: <li>{filter.prefix}</li>
+            </ul>
+        );
+    } else if (filter && "suffix" in filter) {
+        return (
+            <ul class='filter filter-text'>
+                suffix: <li>{filter.suffix}</li>
+            </ul>
+        );
+    } else if (filter && "match" in filter) {
+        return (
+            <ul class='filter filter-text'>
+                match: <li>{filter.match}</li>
+            </ul>
+        );
+    } else if (filter && "regex" in filter) {
+        return (
+            <ul class='filter filter-text'>
+                regex: <li>{filter.regex}</li>
+            </ul>
+        );
+    } else if (filter && "cidr" in filter) {
+        return (
+            <ul class='filter filter-keyword-is'>
+                in: {filter.cidr.map((v) => (<li>{v}</li>))}
+            </ul>
+        );
+    } else if (filter && "is_true" in filter) {
+        return (
+            <ul class='filter filter-keyword-is'>
+                is: <li>{filter.is_true ? "true" : "false"}</li>
+            </ul>
+        );
     } else if (filter && "min" in filter) {
         return (
             <ul class='filter filter-range'>
@@ -182,12 +230,18 @@ function FieldIcon({ type }: { type: FieldType }) {
         case 'number':
             icon = '#';
             break;
+        case 'boolean':
+            icon = '?';
+            break;
         case 'phrase':
             icon = <Icons.CubeOutline />;
             break;
         case 'timestamp':
             icon = <Icons.TimeOutline />;
             break;
+        case 'ip':
+            icon = '@';
+            break;
     }
 
     return (
@@ -291,6 +345,23 @@ function formatDuration(seconds: number) {
     }
 }
 
+function FilterEditBoolean({ fieldName, filter, dispatch }: FilterEditProps) {
+    const current = filter && 'is_true' in filter ? filter.is_true : undefined;
+    const toggle = (value: boolean) => dispatch(value === current
+        ? { type: 'filterClear', field: fieldName }
+        : { type: 'filter', field: fieldName, filter: { is_true: value } }
+    );
+
+    return (
+        <div class='filter-edit'>
+            <div class='toggle-options'>
+                <button onClick={() => toggle(true)} selected={current === true}>True</button>
+                <button onClick={() => toggle(false)} selected={current === false}>False</button>
+            </div>
+        </div>
+    );
+}
+
 function FilterEditKeyword({ fieldName, field, filter, dispatch }: FilterEditProps) {
     const setFilter = (value: string, include: boolean) => dispatch({ type: 'filterKeyword', field: fieldName, value, include });
     const values: string[] = (field.values ?? []).slice();
@@ -1,14 +1,15 @@
 import * as preact from "preact";
 import * as Icons from "./icons";
 import { Res } from "./req";
-import { QueryRes } from "./api";
+import { FieldsRes, QueryRes, Value } from "./api";
 import { State, DispatchFn } from "./state";
 import { useEffect, useState } from "preact/hooks";
-import { classes } from "util";
+import { classes, formatValue, valueText } from "util";
 
 export type TableProps = {
     state: State,
     dispatch: DispatchFn,
+    fields: FieldsRes,
     dataRes: Res<QueryRes>,
 };
 
@@ -18,7 +19,7 @@ type PopoverState = {
     rect: DOMRect,
 };
 
-export function Table({state, dataRes, dispatch}: TableProps) {
+export function Table({state, fields, dataRes, dispatch}: TableProps) {
     const [dropCol, setDropCol] = useState<undefined | number>(undefined);
     const [resizeCol, setResizeCol] = useState<undefined | number>(undefined);
     const [widths, setWidths] = useState < { [key: string]: number }>(() => JSON.parse(localStorage.photonFieldWidths || '{}'));
@@ -73,7 +74,7 @@ export function Table({state, dataRes, dispatch}: TableProps) {
             { popoverState && <TablePopover
                 rect = { popoverState.rect }
                 field = { popoverState.field }
-                value = { data[popoverState.row]?.[popoverState.field] ?? '' }
+                value = { data[popoverState.row]?.[popoverState.field] }
                 dispatch = { dispatch }
                 close = { () => setPopoverState(undefined) }
             /> }
@@ -111,7 +112,7 @@ export function Table({state, dataRes, dispatch}: TableProps) {
                 { data.map(row => (
                     <tr>
                         <td></td>
-                        { state.fields.map(field => <td>{row[field]}</td>) }
+                        { state.fields.map(field => <td>{formatValue(row[field], fields.fields[field]?.unit)}</td>) }
                     </tr>
                 )) }
             </table>
@@ -157,12 +158,14 @@ function ResizeHandle({ onPress, onMove, onRelease, selected }: ResizeHandleProp
 
 type TablePopoverProps = {
     rect: DOMRect,
-    value: string,
+    value: Value | undefined,
     field: string,
     dispatch: DispatchFn,
     close: () => void,
 };
-function TablePopover({rect, value, field, dispatch, close}: TablePopoverProps) {
+function TablePopover({rect, value: rawValue, field, dispatch, close}: TablePopoverProps) {
+    const value = valueText(rawValue);
+
     useEffect(() => {
         const handler = (e: MouseEvent) => {
             if (!(e.target as HTMLElement).closest('.popover')) {
@@ -196,7 +199,7 @@ function TablePopover({rect, value, field, dispatch, close}: TablePopoverProps)
     return (
         <div class='popover' style={{ maxWidth, top: rect.bottom, ...pos }}>
             <div class='value'>
-                {value}
+                {typeof rawValue == 'object' && rawValue != null ? JSON.stringify(rawValue, null, 2) : value}
             </div>
             <div class='toolbar'>
                 <button title="Filter for" onClick={() => setFilter(true)}><Icons.AddCircleOutline /></button>
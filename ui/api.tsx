@@ -8,12 +8,15 @@ export type FieldType =
     | 'keyword'
     | 'phrase'
     | 'number'
+    | 'boolean'
     | 'timestamp'
+    | 'ip'
     ;
 
 export type Field = {
     type: FieldType,
     values?: [string],
+    unit?: string,
 };
 
 export type FilterPresent = { present: boolean };
@@ -22,6 +25,13 @@ export type FilterKeywordNot = { not: string[] };
 export type FilterNumberRange = { min?: number; max?: number };
 export type FilterTimeRange = { after: string, before: string };
 export type FilterTimeSince = { since: number };
+export type FilterContains = { contains: string, case_insensitive?: boolean };
+export type FilterPrefix = { prefix: string };
+export type FilterSuffix = { suffix: string };
+export type FilterMatch = { match: string };
+export type FilterRegex = { regex: string, case_insensitive?: boolean };
+export type FilterCidr = { cidr: string[] };
+export type FilterIsTrue = { is_true: boolean };
 
 export type Filter =
     | undefined
@@ -31,12 +41,66 @@ export type Filter =
     | FilterNumberRange
     | FilterTimeRange
     | FilterTimeSince
+    | FilterContains
+    | FilterPrefix
+    | FilterSuffix
+    | FilterMatch
+    | FilterRegex
+    | FilterCidr
+    | FilterIsTrue
     ;
 
+export type FilterExpr = {
+    and?: FilterExpr[],
+    or?: FilterExpr[],
+    not?: FilterExpr,
+    [field: string]: Filter | FilterExpr | FilterExpr[],
+};
+
 export type FieldsRes = { fields: { [key: string]: Field } };
 
-export type QueryRes = { results: Array<{ [key: string]: string }> };
+export type Aggregation =
+    | 'count'
+    | { terms: { field: string, size?: number } }
+    | { histogram: { field: string, interval: number } }
+    | { date_histogram: { field: string, interval: number } }
+    | { min: { field: string } }
+    | { max: { field: string } }
+    | { avg: { field: string } }
+    | { sum: { field: string } }
+    ;
+
+export type Bucket = { key: string | number, count: number };
+export type AggregationResult = number | null | Bucket[];
+
+export type Sort = { field: string, order?: 'asc' | 'desc' };
+
+export type QueryStats = {
+    rows_scanned: number,
+    rows_matched: number,
+    bytes_read: number,
+    files_opened: number,
+    files_skipped: number,
+    codecs: { [codec: string]: number },
+    parse_failures: { [field: string]: number },
+    elapsed: number,
+};
+
+/// Timestamps are RFC 3339 strings, and objects and arrays are nested JSON
+export type Value = null | string | number | boolean | { [key: string]: Value } | Value[];
+
+export type QueryRes = {
+    stats: QueryStats,
+    results: Array<{ [key: string]: Value }>,
+    aggregations?: { [key: string]: AggregationResult },
+    next?: string,
+};
 export type QueryReq = {
-    filter: {},
+    filter: FilterExpr,
     returning: Array<string>,
+    aggregate?: { [key: string]: Aggregation },
+    sort?: Sort[],
+    limit?: number,
+    offset?: number,
+    after?: string,
 };
\ No newline at end of file
@@ -1,4 +1,5 @@
 import { useEffect,  useState } from "preact/hooks";
+import { Value } from "./api";
 
 export function useEvent(target: EventTarget, event: string, handler: EventListenerOrEventListenerObject) {
     useEffect(() => {
@@ -37,3 +38,46 @@ export function insertOrderedSet<T>(set: T[], val: T, pos?: number): T[] {
     set.splice(pos, 0, val);
     return set;
 }
+
+const byteUnits = ['B', 'KiB', 'MiB', 'GiB', 'TiB', 'PiB'];
+
+function formatNumber(n: number): string {
+    return parseFloat(n.toPrecision(3)).toString();
+}
+
+/// Text of a result value, as used for keyword filters
+export function valueText(value: Value | undefined): string {
+    if (value == null) return '';
+    if (typeof value == 'object') return JSON.stringify(value);
+    return String(value);
+}
+
+/// Render a value in a canonical unit (`s` or `B`) in a readable scale, e.g. `1.5 s` rather than `1.5`
+export function formatValue(value: Value | undefined, unit?: string): string {
+    if (typeof value != 'number' || !unit) return valueText(value);
+    const n = value;
+
+    if (unit == 's') {
+        const abs = Math.abs(n);
+        if (abs == 0) return '0 s';
+        if (abs < 1e-6) return `${formatNumber(n * 1e9)} ns`;
+        if (abs < 1e-3) return `${formatNumber(n * 1e6)} µs`;
+        if (abs < 1) return `${formatNumber(n * 1e3)} ms`;
+        if (abs < 60) return `${formatNumber(n)} s`;
+        if (abs < 3600) return `${formatNumber(n / 60)} min`;
+        if (abs < 86400) return `${formatNumber(n / 3600)} h`;
+        return `${formatNumber(n / 86400)} d`;
+    }
+
+    if (unit == 'B') {
+        let i = 0;
+        let scaled = n;
+        while (Math.abs(scaled) >= 1024 && i < byteUnits.length - 1) {
+            scaled /= 1024;
+            i++;
+        }
+        return `${formatNumber(scaled)} ${byteUnits[i]}`;
+    }
+
+    return `${n} ${unit}`;
+}